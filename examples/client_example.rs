@@ -62,7 +62,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     //打印   request 详细信息
     println!("Request details: {:?}", request); 
     // Fetch with automatic payment handling
-    // The function detects 402 status code, parses payment requirements from x-payment-required header
+    // The function detects 402 status code, parses payment requirements from the 402 body (or x-payment-required header)
     // Creates and signs payment transaction, attaches payment info (x-payment header) and resends request
     match fetcher.fetch(request).await {
         Ok(response) => {
@@ -82,10 +82,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let body = String::from_utf8_lossy(&response.body);
             println!("Body: {}", body);
             
-            // Check for payment response
-            if let Some(payment_response) = response.headers.get("x-payment-response") {
+            // Check for the settlement receipt (X-PAYMENT-RESPONSE)
+            if let Some(receipt) = &response.payment_response {
                 println!();
-                println!("Payment Response: {}", payment_response);
+                println!("Payment settled: {} (signature: {})", receipt.settled, receipt.signature);
             }
             
             println!();
@@ -1,16 +1,16 @@
 // This file demonstrates how to create a Server with payment protection using the x402 SDK.
-// The server uses middleware to check payments before serving protected content.
+// The server wraps its routes in PaymentMiddleware, which checks payments before serving
+// protected content and settles them after a successful response.
 
-use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Responder};
+use actix_web::{web, App, HttpResponse, HttpServer, Responder};
 use serde::Serialize;
+use std::collections::HashMap;
 use std::env;
 use x402_sdk_solana_rust::{
-    check_payment, settle_payment,
-    types::{Network, RouteConfig, TokenConfig},
-    server::PaymentMiddlewareConfig,
+    server::{PaymentMiddleware, PaymentMiddlewareConfig},
+    types::{FacilitatorConfig, Network, RouteConfig, SvmConfig, TokenConfig, X402Config},
 };
 
-#[derive(Clone)]
 struct ServerConfig {
     facilitator_url: String,
     pay_to: String,
@@ -34,15 +34,35 @@ struct PremiumContent {
     content: String,
 }
 
-// Weather endpoint - requires payment
-async fn weather_handler(
-    req: HttpRequest,
-    config: web::Data<ServerConfig>,
-) -> impl Responder {
+// Weather endpoint - requires payment (enforced by PaymentMiddleware)
+async fn weather_handler() -> impl Responder {
     println!("=== Weather endpoint called ===");
 
+    let response = WeatherResponse {
+        report: WeatherReport {
+            weather: "sunny".to_string(),
+            temperature: 70,
+        },
+    };
+
+    HttpResponse::Ok().json(response)
+}
+
+// Premium content endpoint - requires higher payment (enforced by PaymentMiddleware)
+async fn premium_content_handler() -> impl Responder {
+    println!("=== Premium content endpoint called ===");
+
+    let response = PremiumContent {
+        content: "This is premium content".to_string(),
+    };
+
+    HttpResponse::Ok().json(response)
+}
+
+// Build the payment middleware configuration shared by all workers
+fn build_middleware_config(config: &ServerConfig) -> PaymentMiddlewareConfig {
     // Create routes map
-    let mut routes = std::collections::HashMap::new();
+    let mut routes = HashMap::new();
     routes.insert(
         "GET /weather".to_string(),
         RouteConfig {
@@ -52,79 +72,10 @@ async fn weather_handler(
             mime_type: Some("application/json".to_string()),
             max_timeout_seconds: Some(30),
             discoverable: Some(true),
+            pricing: None,
+            accepts: Vec::new(),
         },
     );
-
-    // Build X402 config with token if available
-    let x402_config = config.token_config.as_ref().map(|token| {
-        x402_sdk_solana_rust::types::X402Config {
-            svm_config: Some(x402_sdk_solana_rust::types::SvmConfig {
-                rpc_url: None,
-                default_token: Some(token.clone()),
-            }),
-        }
-    });
-
-    // Create middleware config
-    let middleware_config = PaymentMiddlewareConfig {
-        pay_to: config.pay_to.clone(),
-        routes,
-        facilitator: Some(x402_sdk_solana_rust::types::FacilitatorConfig {
-            url: config.facilitator_url.clone(),
-            create_auth_headers: None,
-        }),
-        x402_config,
-    };
-
-    // Check payment
-    match check_payment(&req, &middleware_config).await {
-        Ok(Some(response)) => {
-            // Payment not provided or verification failed, return the response
-            println!("✗ Payment check failed, returning 402 or error");
-            response
-        }
-        Ok(None) => {
-            // Payment verified successfully
-            println!("✓ Payment verified, settling...");
-
-            // Settle payment
-            if let Err(e) = settle_payment(&req, &middleware_config).await {
-                eprintln!("✗ Payment settlement failed: {:?}", e);
-                return HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": "Payment settlement failed"
-                }));
-            }
-
-            println!("✓ Payment settled successfully");
-
-            // Return protected content
-            let response = WeatherResponse {
-                report: WeatherReport {
-                    weather: "sunny".to_string(),
-                    temperature: 70,
-                },
-            };
-
-            HttpResponse::Ok().json(response)
-        }
-        Err(e) => {
-            eprintln!("✗ Payment check error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Internal server error"
-            }))
-        }
-    }
-}
-
-// Premium content endpoint - requires higher payment
-async fn premium_content_handler(
-    req: HttpRequest,
-    config: web::Data<ServerConfig>,
-) -> impl Responder {
-    println!("=== Premium content endpoint called ===");
-
-    // Create routes map
-    let mut routes = std::collections::HashMap::new();
     routes.insert(
         "GET /premium/content".to_string(),
         RouteConfig {
@@ -134,65 +85,29 @@ async fn premium_content_handler(
             mime_type: Some("application/json".to_string()),
             max_timeout_seconds: Some(60),
             discoverable: Some(true),
+            pricing: None,
+            accepts: Vec::new(),
         },
     );
 
     // Build X402 config with token if available
-    let x402_config = config.token_config.as_ref().map(|token| {
-        x402_sdk_solana_rust::types::X402Config {
-            svm_config: Some(x402_sdk_solana_rust::types::SvmConfig {
-                rpc_url: None,
-                default_token: Some(token.clone()),
-            }),
-        }
+    let x402_config = config.token_config.as_ref().map(|token| X402Config {
+        svm_config: Some(SvmConfig {
+            rpc_url: None,
+            default_token: Some(token.clone()),
+        }),
     });
 
-    // Create middleware config
-    let middleware_config = PaymentMiddlewareConfig {
-        pay_to: config.pay_to.clone(),
+    PaymentMiddlewareConfig::new(
+        config.pay_to.clone(),
         routes,
-        facilitator: Some(x402_sdk_solana_rust::types::FacilitatorConfig {
+        Some(FacilitatorConfig {
             url: config.facilitator_url.clone(),
             create_auth_headers: None,
         }),
         x402_config,
-    };
-
-    // Check payment
-    match check_payment(&req, &middleware_config).await {
-        Ok(Some(response)) => {
-            // Payment not provided or verification failed, return the response
-            println!("✗ Payment check failed, returning 402 or error");
-            response
-        }
-        Ok(None) => {
-            // Payment verified successfully
-            println!("✓ Payment verified, settling...");
-
-            // Settle payment
-            if let Err(e) = settle_payment(&req, &middleware_config).await {
-                eprintln!("✗ Payment settlement failed: {:?}", e);
-                return HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": "Payment settlement failed"
-                }));
-            }
-
-            println!("✓ Payment settled successfully");
-
-            // Return protected content
-            let response = PremiumContent {
-                content: "This is premium content".to_string(),
-            };
-
-            HttpResponse::Ok().json(response)
-        }
-        Err(e) => {
-            eprintln!("✗ Payment check error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Internal server error"
-            }))
-        }
-    }
+    )
+    .expect("Invalid route configuration")
 }
 
 #[actix_web::main]
@@ -257,13 +172,14 @@ async fn main() -> std::io::Result<()> {
 
     println!();
 
-    // Create server configuration
-    let config = web::Data::new(ServerConfig {
+    // Create payment middleware once; every worker shares its configuration
+    let config = ServerConfig {
         facilitator_url,
         pay_to,
         network,
         token_config,
-    });
+    };
+    let payment_middleware = PaymentMiddleware::new(build_middleware_config(&config));
 
     let bind_addr = format!("{}:{}", host, port);
     println!("Starting payment-protected server at http://{}", bind_addr);
@@ -278,7 +194,7 @@ async fn main() -> std::io::Result<()> {
     // Start HTTP server
     HttpServer::new(move || {
         App::new()
-            .app_data(config.clone())
+            .wrap(payment_middleware.clone())
             .route("/weather", web::get().to(weather_handler))
             .route("/premium/content", web::get().to(premium_content_handler))
     })
@@ -1,119 +1,501 @@
+use super::ledger::{transaction_id, LedgerErrorFn, PaymentLedger, PaymentRecord};
+use super::policy::{PaymentAttempt, SpendReservation, SpendingPolicy};
+use super::retry::RetryPolicy;
+use super::selector::{RequirementsSelector, SelectionContext};
 use crate::error::X402Error;
-use crate::solana::{TransactionBuilder, Wallet};
+use crate::solana::{AsyncTransactionBuilder, PaymentSigner, SolanaRpc, TransactionBuilder, Wallet};
 use crate::types::{
-    PaymentPayload, PaymentRequirements, PaymentScheme, X402Config, X402Request, X402Response,
+    PaymentPayload, PaymentRequiredResponse, PaymentRequirements, PaymentScheme, SettleResponse,
+    X402Config, X402Request, X402Response,
 };
+use super::response::X402StreamResponse;
 use reqwest::Client;
-use std::collections::HashMap;
+use solana_client::nonblocking::rpc_client::RpcClient as AsyncRpcClient;
+use std::sync::Arc;
 
-/// Fetcher for making HTTP requests with automatic payment handling
+/// Fetcher for making HTTP requests with automatic payment handling.
+///
+/// Payments are signed by any [`PaymentSigner`], e.g. a [`Wallet`]. Use
+/// [`Fetcher::builder`] to send requests through your own `reqwest::Client`
+/// (proxies, TLS, timeouts, cookies).
 pub struct Fetcher {
     client: Client,
-    wallet: Wallet,
+    signer: Arc<dyn PaymentSigner>,
     max_value: Option<u64>,
     config: Option<X402Config>,
+    rpc: Option<Arc<dyn SolanaRpc>>,
+    policy: Option<Arc<SpendingPolicy>>,
+    ledger: Option<Arc<dyn PaymentLedger>>,
+    ledger_errors: Option<LedgerErrorFn>,
+    retry: RetryPolicy,
+    selector: Option<Arc<dyn RequirementsSelector>>,
+    preflight: bool,
 }
 
-impl Fetcher {
-    /// Create a new Fetcher with wallet
-    pub fn new(wallet: Wallet, config: Option<X402Config>) -> Self {
+/// Builder for a [`Fetcher`]
+///
+/// ```rust,ignore
+/// let client = reqwest::Client::builder()
+///     .proxy(reqwest::Proxy::all("http://proxy.internal:3128")?)
+///     .timeout(Duration::from_secs(30))
+///     .build()?;
+/// let fetcher = Fetcher::builder(wallet)
+///     .client(client)
+///     .max_value(100_000)
+///     .retry(RetryPolicy::new(3))
+///     .build();
+/// ```
+pub struct FetcherBuilder {
+    client: Option<Client>,
+    signer: Arc<dyn PaymentSigner>,
+    max_value: Option<u64>,
+    config: Option<X402Config>,
+    rpc: Option<Arc<dyn SolanaRpc>>,
+    policy: Option<Arc<SpendingPolicy>>,
+    ledger: Option<Arc<dyn PaymentLedger>>,
+    ledger_errors: Option<LedgerErrorFn>,
+    retry: RetryPolicy,
+    selector: Option<Arc<dyn RequirementsSelector>>,
+    preflight: bool,
+}
+
+impl FetcherBuilder {
+    /// Start a fetcher paying with a shared signer
+    pub fn new(signer: Arc<dyn PaymentSigner>) -> Self {
         Self {
-            client: Client::new(),
-            wallet,
+            client: None,
+            signer,
             max_value: None,
-            config,
+            config: None,
+            rpc: None,
+            policy: None,
+            ledger: None,
+            ledger_errors: None,
+            retry: RetryPolicy::none(),
+            selector: None,
+            preflight: true,
         }
     }
 
-    /// Create a new Fetcher with max payment value limit
-    pub fn with_max_value(wallet: Wallet, max_value: u64, config: Option<X402Config>) -> Self {
-        Self {
-            client: Client::new(),
-            wallet,
-            max_value: Some(max_value),
-            config,
+    /// Send requests through this client instead of a default one
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// SDK configuration, e.g. the RPC URL used to build payments
+    pub fn config(mut self, config: X402Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Refuse payments above `max_value` atomic units
+    pub fn max_value(mut self, max_value: u64) -> Self {
+        self.max_value = Some(max_value);
+        self
+    }
+
+    /// See [`Fetcher::with_rpc`]
+    pub fn rpc(mut self, rpc: Arc<dyn SolanaRpc>) -> Self {
+        self.rpc = Some(rpc);
+        self
+    }
+
+    /// See [`Fetcher::with_policy`]
+    pub fn policy(mut self, policy: Arc<SpendingPolicy>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// See [`Fetcher::with_ledger`]
+    pub fn ledger(mut self, ledger: Arc<dyn PaymentLedger>) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+
+    /// See [`Fetcher::with_ledger_errors`]
+    pub fn ledger_errors(mut self, handler: LedgerErrorFn) -> Self {
+        self.ledger_errors = Some(handler);
+        self
+    }
+
+    /// See [`Fetcher::with_retry`]
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// See [`Fetcher::with_selector`]
+    pub fn selector(mut self, selector: Arc<dyn RequirementsSelector>) -> Self {
+        self.selector = Some(selector);
+        self
+    }
+
+    /// See [`Fetcher::with_preflight`]
+    pub fn preflight(mut self, preflight: bool) -> Self {
+        self.preflight = preflight;
+        self
+    }
+
+    /// Build the fetcher
+    pub fn build(self) -> Fetcher {
+        Fetcher {
+            client: self.client.unwrap_or_default(),
+            signer: self.signer,
+            max_value: self.max_value,
+            config: self.config,
+            rpc: self.rpc,
+            policy: self.policy,
+            ledger: self.ledger,
+            ledger_errors: self.ledger_errors,
+            retry: self.retry,
+            selector: self.selector,
+            preflight: self.preflight,
         }
     }
+}
+
+/// Why a request produced no response
+enum SendError {
+    /// The connection could not be made, so the server never saw the request
+    NotSent(X402Error),
+    /// The request may have reached the server
+    Failed(X402Error),
+}
+
+impl Fetcher {
+    /// Create a new Fetcher paying with `signer` (e.g. a [`Wallet`])
+    pub fn new(signer: impl PaymentSigner + 'static, config: Option<X402Config>) -> Self {
+        Self::from_signer(Arc::new(signer), config)
+    }
+
+    /// Create a new Fetcher sharing a signer with other fetchers or tasks
+    pub fn from_signer(signer: Arc<dyn PaymentSigner>, config: Option<X402Config>) -> Self {
+        let mut builder = FetcherBuilder::new(signer);
+        builder.config = config;
+        builder.build()
+    }
+
+    /// Create a new Fetcher with max payment value limit
+    pub fn with_max_value(
+        signer: impl PaymentSigner + 'static,
+        max_value: u64,
+        config: Option<X402Config>,
+    ) -> Self {
+        let mut fetcher = Self::new(signer, config);
+        fetcher.max_value = Some(max_value);
+        fetcher
+    }
+
+    /// Configure a fetcher paying with `signer`, e.g. with a custom `reqwest::Client`
+    pub fn builder(signer: impl PaymentSigner + 'static) -> FetcherBuilder {
+        FetcherBuilder::new(Arc::new(signer))
+    }
+
+    /// Build payments through a custom RPC backend instead of the configured URL
+    pub fn with_rpc(mut self, rpc: Arc<dyn SolanaRpc>) -> Self {
+        self.rpc = Some(rpc);
+        self
+    }
+
+    /// Check every payment against a spending policy before signing it
+    pub fn with_policy(mut self, policy: Arc<SpendingPolicy>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Record every payment and its receipt in a ledger
+    pub fn with_ledger(mut self, ledger: Arc<dyn PaymentLedger>) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+
+    /// Hand payments the ledger fails to record to `handler`.
+    ///
+    /// A ledger failure never fails the request, since the payment is already
+    /// out; without a handler the record is dropped.
+    pub fn with_ledger_errors(mut self, handler: LedgerErrorFn) -> Self {
+        self.ledger_errors = Some(handler);
+        self
+    }
+
+    /// Retry failed paid requests according to `retry`
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Choose among the options in a 402 response with `selector` instead of
+    /// paying the first one
+    pub fn with_selector(mut self, selector: Arc<dyn RequirementsSelector>) -> Self {
+        self.selector = Some(selector);
+        self
+    }
+
+    /// Check balances before signing each payment (on by default).
+    ///
+    /// The payer must hold the amount, the network fee unless the facilitator
+    /// pays it, and rent for any token account the payment creates; otherwise
+    /// the fetcher returns [`X402Error::InsufficientFunds`] without signing.
+    pub fn with_preflight(mut self, preflight: bool) -> Self {
+        self.preflight = preflight;
+        self
+    }
 
     /// Fetch data with automatic payment handling
     pub async fn fetch(&self, request: X402Request) -> Result<X402Response, X402Error> {
+        self.fetch_stream(request).await?.into_response().await
+    }
+
+    /// Like [`fetch`](Self::fetch), but hands back the final response before
+    /// reading its body so it can be streamed
+    pub async fn fetch_stream(&self, request: X402Request) -> Result<X402StreamResponse, X402Error> {
         // First attempt - send request without payment
         let response = self.send_request(&request).await?;
 
         // Check if payment is required (402 status)
         if response.status == 402 {
-            // Parse payment requirements from X-PAYMENT-REQUIRED header
-            let payment_required = response
+            return self.pay(&request, response.into_response().await?).await;
+        }
+
+        Ok(response)
+    }
+
+    /// Pay for a request answered with `payment_required` and resend it,
+    /// retrying as allowed by the [`RetryPolicy`]
+    async fn pay(
+        &self,
+        request: &X402Request,
+        mut payment_required: X402Response,
+    ) -> Result<X402StreamResponse, X402Error> {
+        let mut retries = 0;
+        loop {
+            let requirements = self
+                .select_requirements(&request.method, &request.url, &payment_required)
+                .await?;
+
+            // Let the spending policy refuse the payment before anything is signed
+            let reservation = self.authorize(&request.method, &request.url, &requirements).await?;
+
+            // Create payment; an unsigned payment does not count against the budgets
+            let payload = match self.create_payload(&requirements).await {
+                Ok(payment) => payment,
+                Err(e) => {
+                    self.release(reservation);
+                    return Err(e);
+                }
+            };
+
+            // Resend request with payment
+            let mut paid_request = request.clone();
+            paid_request
                 .headers
-                .get("x-payment-required")
-                .ok_or_else(|| {
-                    X402Error::PaymentRequired(
-                        "402 response missing x-payment-required header".to_string(),
-                    )
-                })?;
-
-            let requirements: PaymentRequirements = serde_json::from_str(payment_required)
-                .map_err(|e| X402Error::DeserializationError(e.to_string()))?;
-
-            // Verify payment amount doesn't exceed max_value
-            if let Some(max) = self.max_value {
-                // Parse amount using the same logic as in create_payment
-                let amount: u64 = if requirements.max_amount_required.contains('.') {
-                    // Decimal format (e.g., "0.0018" SOL or tokens)
-                    let decimal_amount: f64 = requirements
-                        .max_amount_required
-                        .parse()
-                        .map_err(|e| X402Error::InvalidInput(format!("Invalid decimal amount: {}", e)))?;
-                    
-                    // Determine decimals based on token or SOL
-                    let decimals = if let Some(token_decimals) = requirements.token_decimals {
-                        token_decimals as u32
-                    } else {
-                        9 // SOL has 9 decimals (lamports)
-                    };
-                    
-                    // Convert to atomic units
-                    (decimal_amount * 10_f64.powi(decimals as i32)) as u64
-                } else {
-                    // Already in atomic units
-                    requirements
-                        .max_amount_required
-                        .parse()
-                        .map_err(|e| X402Error::InvalidInput(format!("Invalid amount: {}", e)))?
+                .insert("x-payment".to_string(), payload.to_header()?);
+            let record = |status, settlement| {
+                self.record(&request.method, &request.url, &requirements, &payload, status, settlement)
+            };
+
+            // Why this payload may already have settled, once it might have reached the server
+            let mut maybe_settled: Option<String> = None;
+            let refused = loop {
+                let failure = match self.try_send(&paid_request).await {
+                    Ok(response) if response.status == 402 => break response,
+                    Ok(response) if response.status >= 500 && self.retry.retry_server_errors => {
+                        format!("server answered {}", response.status)
+                    }
+                    Ok(response) => {
+                        record(Some(response.status), response.payment_response.clone()).await;
+                        // A failed request with no receipt was not settled, so it is not spent
+                        let settled = (200..300).contains(&response.status)
+                            || response.payment_response.as_ref().is_some_and(|r| r.settled);
+                        if !settled && maybe_settled.is_none() {
+                            self.release(reservation);
+                        }
+                        return Ok(response);
+                    }
+                    Err(SendError::NotSent(e)) => {
+                        if retries < self.retry.max_retries {
+                            tokio::time::sleep(self.retry.backoff(retries)).await;
+                            retries += 1;
+                            continue;
+                        }
+                        return Err(match maybe_settled {
+                            Some(reason) => {
+                                record(None, None).await;
+                                X402Error::PaymentMayHaveSettled {
+                                    signature: transaction_id(&payload),
+                                    reason,
+                                }
+                            }
+                            None => {
+                                self.release(reservation);
+                                X402Error::PaymentNotSubmitted(e.to_string())
+                            }
+                        });
+                    }
+                    Err(SendError::Failed(e)) => e.to_string(),
                 };
-                
-                if amount > max {
-                    return Err(X402Error::PaymentAmountExceeded {
-                        expected: max,
-                        got: amount,
+
+                if retries >= self.retry.max_retries {
+                    record(None, None).await;
+                    return Err(X402Error::PaymentMayHaveSettled {
+                        signature: transaction_id(&payload),
+                        reason: failure,
                     });
                 }
+                // Resending the same transaction cannot charge twice
+                maybe_settled = Some(failure);
+                tokio::time::sleep(self.retry.backoff(retries)).await;
+                retries += 1;
+            };
+
+            // The server verified this payment and refused it, so it did not settle it
+            record(Some(refused.status), refused.payment_response.clone()).await;
+            if let Some(reason) = maybe_settled {
+                // ...but an earlier delivery of the same payment may have been settled
+                return Err(X402Error::PaymentMayHaveSettled {
+                    signature: transaction_id(&payload),
+                    reason: format!("{}; the resent payment was refused", reason),
+                });
+            }
+            self.release(reservation);
+            if !self.retry.renegotiate || retries >= self.retry.max_retries {
+                return Ok(refused);
             }
 
-            // Create payment
-            let payment = self.create_payment(&requirements).await?;
+            // Sign a new payment with a fresh blockhash against the latest requirements
+            retries += 1;
+            payment_required = refused.into_response().await?;
+        }
+    }
 
-            // Resend request with payment
-            let mut paid_request = request.clone();
-            paid_request
-                .headers
-                .insert("x-payment".to_string(), payment);
+    /// Pick the payment option to pay from a 402 response
+    pub(crate) async fn select_requirements(
+        &self,
+        method: &str,
+        url: &str,
+        response: &X402Response,
+    ) -> Result<PaymentRequirements, X402Error> {
+        // Parse payment requirements from the 402 body or X-PAYMENT-REQUIRED header
+        let options = Self::payment_requirements(response)?;
 
-            return self.send_request(&paid_request).await;
+        if let Some(selector) = &self.selector {
+            let context = SelectionContext {
+                method,
+                url,
+                payer: self.signer.address(),
+                rpc: self.rpc(),
+                max_value: self.max_value,
+                policy: self.policy.as_deref(),
+            };
+            return selector.select(&options, &context).await;
         }
 
-        Ok(response)
+        let requirements = options.into_iter().next().ok_or_else(|| {
+            X402Error::PaymentRequired("402 response offers no payment options".to_string())
+        })?;
+
+        // Verify payment amount doesn't exceed max_value
+        if let Some(max) = self.max_value {
+            let amount = requirements.amount()?.atomic();
+            if amount > max {
+                return Err(X402Error::PaymentAmountExceeded {
+                    expected: max,
+                    got: amount,
+                });
+            }
+        }
+        Ok(requirements)
     }
 
-    /// Send HTTP request
-    async fn send_request(&self, request: &X402Request) -> Result<X402Response, X402Error> {
-        let method = match request.method.as_str() {
-            "GET" => reqwest::Method::GET,
-            "POST" => reqwest::Method::POST,
-            "PUT" => reqwest::Method::PUT,
-            "DELETE" => reqwest::Method::DELETE,
-            _ => reqwest::Method::GET,
+    /// Check a payment against the spending policy, reserving it against the budgets
+    pub(crate) async fn authorize(
+        &self,
+        method: &str,
+        url: &str,
+        requirements: &PaymentRequirements,
+    ) -> Result<Option<SpendReservation>, X402Error> {
+        match &self.policy {
+            Some(policy) => {
+                let attempt = PaymentAttempt::new(method, url, requirements)?;
+                Ok(Some(policy.authorize(&attempt).await?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Return a reservation from [`authorize`](Self::authorize) for a payment that was not sent
+    pub(crate) fn release(&self, reservation: Option<SpendReservation>) {
+        if let (Some(policy), Some(reservation)) = (&self.policy, reservation) {
+            policy.release(reservation);
+        }
+    }
+
+    /// Record a sent payment and the paid response's status and receipt, if a
+    /// ledger is configured
+    pub(crate) async fn record(
+        &self,
+        method: &str,
+        url: &str,
+        requirements: &PaymentRequirements,
+        payload: &PaymentPayload,
+        status: Option<u16>,
+        settlement: Option<SettleResponse>,
+    ) {
+        let Some(ledger) = &self.ledger else {
+            return;
         };
+        let record = PaymentRecord::new(method, url, requirements, payload, status, settlement);
+        // The payment is already out; a ledger failure must not hide the response
+        if let Err(e) = ledger.record(record.clone()).await {
+            if let Some(handler) = &self.ledger_errors {
+                handler.report(record, e);
+            }
+        }
+    }
+
+    /// Extract the accepted payment options from a 402 response.
+    ///
+    /// Prefers the spec JSON body (`{x402Version, error, accepts}`) and falls back
+    /// to the single requirements object in the `x-payment-required` header.
+    pub(crate) fn payment_requirements(
+        response: &X402Response,
+    ) -> Result<Vec<PaymentRequirements>, X402Error> {
+        if let Ok(body) = serde_json::from_slice::<PaymentRequiredResponse>(&response.body) {
+            return Ok(body.accepts);
+        }
+
+        let payment_required = response
+            .headers
+            .get("x-payment-required")
+            .ok_or_else(|| {
+                X402Error::PaymentRequired(
+                    "402 response has neither an accepts body nor an x-payment-required header"
+                        .to_string(),
+                )
+            })?;
+
+        let requirements: PaymentRequirements = serde_json::from_str(payment_required)
+            .map_err(|e| X402Error::DeserializationError(e.to_string()))?;
+        Ok(vec![requirements])
+    }
+
+    /// Send HTTP request
+    async fn send_request(&self, request: &X402Request) -> Result<X402StreamResponse, X402Error> {
+        self.try_send(request).await.map_err(|e| match e {
+            SendError::NotSent(e) | SendError::Failed(e) => e,
+        })
+    }
+
+    /// Send HTTP request, telling apart requests that never left the client
+    async fn try_send(&self, request: &X402Request) -> Result<X402StreamResponse, SendError> {
+        let method = reqwest::Method::from_bytes(request.method.to_ascii_uppercase().as_bytes())
+            .map_err(|_| {
+                SendError::NotSent(X402Error::InvalidInput(format!(
+                    "Invalid HTTP method: {:?}",
+                    request.method
+                )))
+            })?;
 
         let mut req_builder = self.client.request(method, &request.url);
 
@@ -122,70 +504,70 @@ impl Fetcher {
             req_builder = req_builder.header(key, value);
         }
 
-        // Add body if present
+        // Add body if present; every attempt gets its own copy or stream
         if let Some(body) = &request.body {
-            req_builder = req_builder.body(body.clone());
+            req_builder = req_builder.body(body.to_body());
         }
 
-        let response = req_builder.send().await?;
-
-        // Extract response details
-        let status = response.status().as_u16();
-        let mut headers = HashMap::new();
-        for (key, value) in response.headers() {
-            if let Ok(val_str) = value.to_str() {
-                headers.insert(key.to_string(), val_str.to_string());
+        let response = req_builder.send().await.map_err(|e| {
+            if e.is_connect() || e.is_builder() {
+                SendError::NotSent(e.into())
+            } else {
+                SendError::Failed(e.into())
             }
-        }
-        let body = response.bytes().await?.to_vec();
+        })?;
 
-        Ok(X402Response {
-            status,
-            headers,
-            body,
-        })
+        Ok(X402StreamResponse::new(response))
     }
 
-    /// Create payment payload for the requirements
+    /// Create an `X-PAYMENT` header value for the requirements
     async fn create_payment(
         &self,
         requirements: &PaymentRequirements,
     ) -> Result<String, X402Error> {
-        // Get RPC URL from config or use default based on network
-        let rpc_url = self
-            .config
-            .as_ref()
-            .and_then(|c| c.svm_config.as_ref())
-            .and_then(|s| s.rpc_url.as_ref())
-            .map(|s| s.as_str())
-            .unwrap_or("https://api.devnet.solana.com");
-
-        let tx_builder = TransactionBuilder::new(rpc_url);
-
-        // Parse amount - handle both decimal (e.g., "0.0018") and atomic units (e.g., "1800000")
-        let amount: u64 = if requirements.max_amount_required.contains('.') {
-            // Decimal format (e.g., "0.0018" SOL or tokens)
-            let decimal_amount: f64 = requirements
-                .max_amount_required
-                .parse()
-                .map_err(|e| X402Error::InvalidInput(format!("Invalid decimal amount: {}", e)))?;
-            
-            // Determine decimals based on token or SOL
-            let decimals = if let Some(token_decimals) = requirements.token_decimals {
-                token_decimals as u32
-            } else {
-                9 // SOL has 9 decimals (lamports)
-            };
-            
-            // Convert to atomic units
-            (decimal_amount * 10_f64.powi(decimals as i32)) as u64
-        } else {
-            // Already in atomic units
-            requirements
-                .max_amount_required
-                .parse()
-                .map_err(|e| X402Error::InvalidInput(format!("Invalid amount: {}", e)))?
-        };
+        self.create_payload(requirements).await?.to_header()
+    }
+
+    /// RPC backend payments are built with: the custom one, or the configured URL
+    fn rpc(&self) -> Arc<dyn SolanaRpc> {
+        match &self.rpc {
+            Some(rpc) => rpc.clone(),
+            None => {
+                // Get RPC URL from config or use default based on network
+                let rpc_url = self
+                    .config
+                    .as_ref()
+                    .and_then(|c| c.svm_config.as_ref())
+                    .and_then(|s| s.rpc_url.as_ref())
+                    .map(|s| s.as_str())
+                    .unwrap_or("https://api.devnet.solana.com");
+                Arc::new(AsyncRpcClient::new(rpc_url.to_string()))
+            }
+        }
+    }
+
+    /// Create payment payload for the requirements
+    pub(crate) async fn create_payload(
+        &self,
+        requirements: &PaymentRequirements,
+    ) -> Result<PaymentPayload, X402Error> {
+        let mut tx_builder = AsyncTransactionBuilder::from_rpc(self.rpc());
+
+        // Commit to the server-issued nonce so the payment cannot be replayed
+        if let Some(nonce) = &requirements.nonce {
+            tx_builder = tx_builder.with_memo(nonce.clone());
+        }
+
+        // Let the facilitator pay the network fee when it offers to
+        if let Some(fee_payer) = requirements.fee_payer() {
+            let fee_payer = fee_payer.parse().map_err(|e| {
+                X402Error::InvalidInput(format!("Invalid fee payer address: {}", e))
+            })?;
+            tx_builder = tx_builder.with_fee_payer(fee_payer);
+        }
+
+        // Exact atomic units, whether the price was quoted as "1800000" or "0.0018"
+        let amount = requirements.amount()?.atomic();
 
         // Parse recipient address
         let to_pubkey = requirements.pay_to.parse().map_err(|e| {
@@ -193,58 +575,59 @@ impl Fetcher {
         })?;
 
         // Create transaction - choose between SOL transfer or Token transfer
-        let transaction = if let Some(token_address) = &requirements.token_address {
+        let transaction = if let Some(token_address) = &requirements.asset {
             // Token transfer (USDC, SPL Token, etc.)
-            println!("🪙 Token payment requested:");
-            println!("  Token: {} ({})", 
-                requirements.token_name.as_deref().unwrap_or("Unknown"),
-                token_address
-            );
-            println!("  Amount: {} (atomic units)", amount);
-            println!("  Decimals: {}", requirements.token_decimals.unwrap_or(9));
-            
             let token_pubkey = token_address.parse().map_err(|e| {
                 X402Error::InvalidInput(format!("Invalid token address: {}", e))
             })?;
             
-            let decimals = requirements.token_decimals.unwrap_or(9);
+            let decimals = requirements.token_decimals().unwrap_or(9);
+
+            if self.preflight {
+                tx_builder
+                    .check_spl_token_payment_funds(
+                        &self.signer.address(),
+                        &to_pubkey,
+                        &token_pubkey,
+                        amount,
+                        decimals,
+                    )
+                    .await?;
+            }
             
-            tx_builder.create_spl_token_payment(
-                self.wallet.keypair(),
-                &to_pubkey,
-                &token_pubkey,
-                amount,
-                decimals,
-            )?
+            tx_builder
+                .create_spl_token_payment(
+                    self.signer.as_ref(),
+                    &to_pubkey,
+                    &token_pubkey,
+                    amount,
+                    decimals,
+                )
+                .await?
         } else {
             // SOL transfer
-            println!("💰 SOL payment requested:");
-            println!("  Amount: {} lamports ({} SOL)", amount, amount as f64 / 1_000_000_000.0);
+            if self.preflight {
+                tx_builder
+                    .check_payment_funds(&self.signer.address(), &to_pubkey, amount)
+                    .await?;
+            }
             
-            tx_builder.create_payment_transaction(
-                self.wallet.keypair(),
-                &to_pubkey,
-                amount,
-            )?
+            tx_builder
+                .create_payment_transaction(self.signer.as_ref(), &to_pubkey, amount)
+                .await?
         };
 
         // Serialize transaction to base64
         let signed_tx = TransactionBuilder::serialize_transaction(&transaction)?;
 
         // Create payment payload
-        let payload = PaymentPayload {
+        Ok(PaymentPayload {
             x402_version: 1,
             scheme: PaymentScheme::Exact,
             network: requirements.network.clone(),
             signed_transaction: signed_tx,
-            from: self.wallet.public_key().to_string(),
-        };
-
-        // Serialize payload to JSON string
-        let payload_json = serde_json::to_string(&payload)
-            .map_err(|e| X402Error::SerializationError(e.to_string()))?;
-
-        Ok(payload_json)
+            from: self.signer.address().to_string(),
+        })
     }
 }
 
@@ -257,11 +640,3 @@ pub async fn create_payment_header(
     let fetcher = Fetcher::new(wallet.clone(), config.cloned());
     fetcher.create_payment(requirements).await
 }
-
-impl Clone for Wallet {
-    fn clone(&self) -> Self {
-        // Note: This creates a new keypair, not a true clone
-        // In production, you'd want to properly clone the keypair
-        Self::new()
-    }
-}
\ No newline at end of file
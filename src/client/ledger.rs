@@ -0,0 +1,380 @@
+use crate::error::X402Error;
+use crate::solana::TransactionBuilder;
+use crate::types::{PaymentPayload, PaymentRequirements, SettleResponse};
+use crate::utils::generate_id;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::Signature;
+use std::path::{Path, PathBuf};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+
+/// A payment made by a [`Fetcher`](super::Fetcher), with its receipt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRecord {
+    pub id: String,
+    /// Unix time in seconds when the payment was sent
+    pub timestamp: u64,
+    pub method: String,
+    pub url: String,
+    pub requirements: PaymentRequirements,
+    pub payload: PaymentPayload,
+    /// Transaction signature, from the settlement receipt when there is one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Status of the paid request, or `None` if no response arrived
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Settlement receipt from `X-PAYMENT-RESPONSE`, if the server sent one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settlement: Option<SettleResponse>,
+}
+
+impl PaymentRecord {
+    /// Record a sent payment; `status` and `settlement` describe the paid response
+    pub fn new(
+        method: &str,
+        url: &str,
+        requirements: &PaymentRequirements,
+        payload: &PaymentPayload,
+        status: Option<u16>,
+        settlement: Option<SettleResponse>,
+    ) -> Self {
+        let signature = settlement
+            .as_ref()
+            .map(|settlement| settlement.signature.clone())
+            .filter(|signature| !signature.is_empty())
+            .or_else(|| transaction_id(payload));
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            id: generate_id(),
+            timestamp,
+            method: method.to_ascii_uppercase(),
+            url: url.to_string(),
+            requirements: requirements.clone(),
+            payload: payload.clone(),
+            signature,
+            status,
+            settlement,
+        }
+    }
+
+    /// Whether the server reported the payment as settled on-chain
+    pub fn settled(&self) -> bool {
+        self.settlement.as_ref().is_some_and(|settlement| settlement.settled)
+    }
+}
+
+/// Signature identifying the payment transaction on-chain.
+///
+/// A sponsored transaction has no id until the fee payer signs it at settlement.
+pub(crate) fn transaction_id(payload: &PaymentPayload) -> Option<String> {
+    TransactionBuilder::deserialize_transaction(&payload.signed_transaction)
+        .ok()
+        .and_then(|transaction| transaction.signatures.first().copied())
+        .filter(|signature| *signature != Signature::default())
+        .map(|signature| signature.to_string())
+}
+
+/// Filter for [`PaymentLedger::query`]; an empty query matches every record
+#[derive(Debug, Clone, Default)]
+pub struct PaymentQuery {
+    since: Option<u64>,
+    until: Option<u64>,
+    url_prefix: Option<String>,
+    pay_to: Option<String>,
+    asset: Option<Option<String>>,
+    settled: Option<bool>,
+}
+
+impl PaymentQuery {
+    /// Match every record
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Payments made at or after this Unix time
+    pub fn since(mut self, timestamp: u64) -> Self {
+        self.since = Some(timestamp);
+        self
+    }
+
+    /// Payments made before this Unix time
+    pub fn until(mut self, timestamp: u64) -> Self {
+        self.until = Some(timestamp);
+        self
+    }
+
+    /// Payments for URLs starting with `prefix`
+    pub fn url_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.url_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Payments to this recipient
+    pub fn pay_to(mut self, pay_to: impl Into<String>) -> Self {
+        self.pay_to = Some(pay_to.into());
+        self
+    }
+
+    /// Payments in `asset`: a token mint, or `None` for SOL
+    pub fn asset(mut self, asset: Option<&str>) -> Self {
+        self.asset = Some(asset.map(str::to_string));
+        self
+    }
+
+    /// Only settled (`true`) or only unsettled (`false`) payments
+    pub fn settled(mut self, settled: bool) -> Self {
+        self.settled = Some(settled);
+        self
+    }
+
+    /// Whether `record` passes every filter
+    pub fn matches(&self, record: &PaymentRecord) -> bool {
+        self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp < until)
+            && self
+                .url_prefix
+                .as_ref()
+                .is_none_or(|prefix| record.url.starts_with(prefix.as_str()))
+            && self
+                .pay_to
+                .as_ref()
+                .is_none_or(|pay_to| record.requirements.pay_to == *pay_to)
+            && self
+                .asset
+                .as_ref()
+                .is_none_or(|asset| record.requirements.asset == *asset)
+            && self.settled.is_none_or(|settled| record.settled() == settled)
+    }
+}
+
+/// Storage for the payments a [`Fetcher`](super::Fetcher) makes.
+///
+/// A record is written for every payment that was sent, including ones whose
+/// paid request failed, since money may have moved either way.
+#[async_trait]
+pub trait PaymentLedger: Send + Sync {
+    /// Store a payment
+    async fn record(&self, record: PaymentRecord) -> Result<(), X402Error>;
+
+    /// Stored payments matching `query`, oldest first
+    async fn query(&self, query: &PaymentQuery) -> Result<Vec<PaymentRecord>, X402Error>;
+}
+
+/// Callback told about payments a [`PaymentLedger`] failed to store, e.g. to
+/// keep them elsewhere or alert someone.
+///
+/// The payment has been sent by then, so the failure does not fail the request.
+#[derive(Clone)]
+pub struct LedgerErrorFn(Arc<dyn Fn(PaymentRecord, X402Error) + Send + Sync>);
+
+impl LedgerErrorFn {
+    /// Wrap a callback receiving the unrecorded payment and the ledger's error
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(PaymentRecord, X402Error) + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    /// Report a payment that could not be recorded
+    pub fn report(&self, record: PaymentRecord, error: X402Error) {
+        (self.0)(record, error)
+    }
+}
+
+impl fmt::Debug for LedgerErrorFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LedgerErrorFn")
+    }
+}
+
+/// In-memory [`PaymentLedger`], e.g. for tests or short-lived processes
+#[derive(Debug, Default)]
+pub struct InMemoryPaymentLedger {
+    records: Mutex<Vec<PaymentRecord>>,
+}
+
+impl InMemoryPaymentLedger {
+    /// Create an empty ledger
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl PaymentLedger for InMemoryPaymentLedger {
+    async fn record(&self, record: PaymentRecord) -> Result<(), X402Error> {
+        self.records
+            .lock()
+            .map_err(|e| X402Error::InternalError(format!("Payment ledger poisoned: {}", e)))?
+            .push(record);
+        Ok(())
+    }
+
+    async fn query(&self, query: &PaymentQuery) -> Result<Vec<PaymentRecord>, X402Error> {
+        let records = self
+            .records
+            .lock()
+            .map_err(|e| X402Error::InternalError(format!("Payment ledger poisoned: {}", e)))?;
+        Ok(records.iter().filter(|record| query.matches(record)).cloned().collect())
+    }
+}
+
+/// [`PaymentLedger`] that appends one JSON record per line to a file.
+///
+/// Records survive restarts and the file can be shared with other tools; it is
+/// created on first use and never rewritten.
+#[derive(Debug)]
+pub struct FilePaymentLedger {
+    path: PathBuf,
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl FilePaymentLedger {
+    /// Store payments in the JSON-lines file at `path`
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Path of the ledger file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[async_trait]
+impl PaymentLedger for FilePaymentLedger {
+    async fn record(&self, record: PaymentRecord) -> Result<(), X402Error> {
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+
+        // One writer at a time so lines from concurrent payments do not interleave
+        let _guard = self.lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|e| {
+                X402Error::InternalError(format!("Cannot open {}: {}", self.path.display(), e))
+            })?;
+        file.write_all(line.as_bytes()).await.map_err(|e| {
+            X402Error::InternalError(format!("Cannot write {}: {}", self.path.display(), e))
+        })?;
+        file.flush().await.map_err(|e| {
+            X402Error::InternalError(format!("Cannot write {}: {}", self.path.display(), e))
+        })
+    }
+
+    async fn query(&self, query: &PaymentQuery) -> Result<Vec<PaymentRecord>, X402Error> {
+        let contents = {
+            let _guard = self.lock.lock().await;
+            match tokio::fs::read_to_string(&self.path).await {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(e) => {
+                    return Err(X402Error::InternalError(format!(
+                        "Cannot read {}: {}",
+                        self.path.display(),
+                        e
+                    )))
+                }
+            }
+        };
+
+        let mut records = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: PaymentRecord = serde_json::from_str(line).map_err(|e| {
+                X402Error::DeserializationError(format!(
+                    "{} line {}: {}",
+                    self.path.display(),
+                    index + 1,
+                    e
+                ))
+            })?;
+            if query.matches(&record) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Columns written by [`export_csv`]
+const CSV_HEADER: &str = "id,timestamp,method,url,network,payTo,asset,amount,decimals,payer,signature,status,settled,message";
+
+/// Quote a CSV field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Export records as CSV with one row per payment and amounts in atomic units.
+///
+/// The asset column holds the token mint, or `SOL` for native payments.
+pub fn export_csv(records: &[PaymentRecord]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+    for record in records {
+        let requirements = &record.requirements;
+        let network = serde_json::to_value(&requirements.network)
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let amount = requirements
+            .amount()
+            .map(|amount| amount.to_atomic_string())
+            .unwrap_or_else(|_| requirements.max_amount_required.clone());
+        let decimals = requirements
+            .amount()
+            .map(|amount| amount.decimals().to_string())
+            .unwrap_or_default();
+        let fields = [
+            record.id.clone(),
+            record.timestamp.to_string(),
+            record.method.clone(),
+            record.url.clone(),
+            network,
+            requirements.pay_to.clone(),
+            requirements.asset.clone().unwrap_or_else(|| "SOL".to_string()),
+            amount,
+            decimals,
+            record.payload.from.clone(),
+            record.signature.clone().unwrap_or_default(),
+            record.status.map(|status| status.to_string()).unwrap_or_default(),
+            record.settled().to_string(),
+            record
+                .settlement
+                .as_ref()
+                .and_then(|settlement| settlement.message.clone())
+                .unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Export records as a pretty-printed JSON array
+pub fn export_json(records: &[PaymentRecord]) -> Result<String, X402Error> {
+    serde_json::to_string_pretty(records).map_err(|e| X402Error::SerializationError(e.to_string()))
+}
@@ -0,0 +1,130 @@
+use super::fetcher::Fetcher;
+use super::ledger::transaction_id;
+use super::response::X402StreamResponse;
+use crate::error::X402Error;
+use crate::types::SettleResponse;
+use async_trait::async_trait;
+use http::Extensions;
+use reqwest::header::HeaderValue;
+use reqwest::{Request, Response, StatusCode};
+use reqwest_middleware::{Error, Middleware, Next};
+use std::sync::Arc;
+
+/// [`reqwest_middleware::Middleware`] that pays for `402 Payment Required` responses.
+///
+/// Adding it to a `reqwest_middleware::ClientWithMiddleware` gives existing
+/// reqwest code x402 support without changing call sites. A request answered
+/// with a 402 is paid and sent once more with an `X-PAYMENT` header. The
+/// payment is built by the wrapped [`Fetcher`], so its signer, RPC backend,
+/// `max_value`, selector, spending policy and payment ledger all apply.
+///
+/// Requests whose body cannot be cloned (streams), and requests that already
+/// carry an `X-PAYMENT` header, are passed through unchanged.
+///
+/// ```rust,ignore
+/// let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+///     .with(X402Middleware::new(Fetcher::new(wallet, None)))
+///     .build();
+/// let weather = client.get("https://api.example.com/weather").send().await?;
+/// ```
+#[derive(Clone)]
+pub struct X402Middleware {
+    fetcher: Arc<Fetcher>,
+}
+
+impl X402Middleware {
+    /// Pay with `fetcher`'s signer and settings
+    pub fn new(fetcher: Fetcher) -> Self {
+        Self::from_fetcher(Arc::new(fetcher))
+    }
+
+    /// Pay with a fetcher that is also used elsewhere, sharing its budgets and ledger
+    pub fn from_fetcher(fetcher: Arc<Fetcher>) -> Self {
+        Self { fetcher }
+    }
+}
+
+#[async_trait]
+impl Middleware for X402Middleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        // Keep a copy to resend with the payment; streaming bodies cannot be replayed
+        let replay = if req.headers().contains_key("x-payment") {
+            None
+        } else {
+            req.try_clone()
+        };
+        let response = next.clone().run(req, extensions).await?;
+        let Some(mut paid_request) =
+            replay.filter(|_| response.status() == StatusCode::PAYMENT_REQUIRED)
+        else {
+            return Ok(response);
+        };
+
+        let method = paid_request.method().to_string();
+        let url = paid_request.url().to_string();
+        let payment_required = X402StreamResponse::new(response)
+            .into_response()
+            .await
+            .map_err(Error::middleware)?;
+        let requirements = self
+            .fetcher
+            .select_requirements(&method, &url, &payment_required)
+            .await
+            .map_err(Error::middleware)?;
+
+        // Same checks and signing as Fetcher::fetch
+        let reservation = self
+            .fetcher
+            .authorize(&method, &url, &requirements)
+            .await
+            .map_err(Error::middleware)?;
+        let payload = match self.fetcher.create_payload(&requirements).await {
+            Ok(payload) => payload,
+            Err(e) => {
+                self.fetcher.release(reservation);
+                return Err(Error::middleware(e));
+            }
+        };
+        let header = payload
+            .to_header()
+            .and_then(|header| {
+                HeaderValue::from_str(&header)
+                    .map_err(|e| X402Error::SerializationError(e.to_string()))
+            })
+            .map_err(Error::middleware)?;
+        paid_request.headers_mut().insert("x-payment", header);
+
+        match next.run(paid_request, extensions).await {
+            Ok(response) => {
+                let settlement = response
+                    .headers()
+                    .get("x-payment-response")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| SettleResponse::from_header(value).ok());
+                let status = response.status().as_u16();
+                self.fetcher
+                    .record(&method, &url, &requirements, &payload, Some(status), settlement)
+                    .await;
+                Ok(response)
+            }
+            Err(Error::Reqwest(e)) if e.is_connect() || e.is_builder() => {
+                self.fetcher.release(reservation);
+                Err(Error::middleware(X402Error::PaymentNotSubmitted(e.to_string())))
+            }
+            Err(e) => {
+                self.fetcher
+                    .record(&method, &url, &requirements, &payload, None, None)
+                    .await;
+                Err(Error::middleware(X402Error::PaymentMayHaveSettled {
+                    signature: transaction_id(&payload),
+                    reason: e.to_string(),
+                }))
+            }
+        }
+    }
+}
@@ -1,3 +1,25 @@
 mod fetcher;
+mod ledger;
+#[cfg(feature = "reqwest-middleware")]
+mod middleware;
+mod policy;
+mod response;
+mod retry;
+mod selector;
 
-pub use fetcher::{Fetcher, create_payment_header};
\ No newline at end of file
+pub use fetcher::{Fetcher, FetcherBuilder, create_payment_header};
+pub use ledger::{
+    export_csv, export_json, FilePaymentLedger, InMemoryPaymentLedger, LedgerErrorFn,
+    PaymentLedger, PaymentQuery, PaymentRecord,
+};
+pub use policy::{
+    ApprovalFn, ApprovalFuture, BudgetWindow, PaymentApproval, PaymentAttempt, PolicyDenial,
+    SpendReservation, SpendingPolicy,
+};
+#[cfg(feature = "reqwest-middleware")]
+pub use middleware::X402Middleware;
+pub use response::X402StreamResponse;
+pub use retry::RetryPolicy;
+pub use selector::{
+    BalanceAwareSelector, RejectedOption, RejectionReason, RequirementsSelector, SelectionContext,
+};
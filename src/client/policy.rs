@@ -0,0 +1,446 @@
+use crate::error::X402Error;
+use crate::server::RoutePattern;
+use crate::types::{Amount, PaymentRequirements};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Rolling period a spending budget applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BudgetWindow {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl BudgetWindow {
+    /// Length of the window
+    pub fn duration(&self) -> Duration {
+        match self {
+            BudgetWindow::Minute => Duration::from_secs(60),
+            BudgetWindow::Hour => Duration::from_secs(60 * 60),
+            BudgetWindow::Day => Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+impl fmt::Display for BudgetWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetWindow::Minute => f.write_str("minute"),
+            BudgetWindow::Hour => f.write_str("hour"),
+            BudgetWindow::Day => f.write_str("day"),
+        }
+    }
+}
+
+/// A payment the [`Fetcher`](super::Fetcher) is about to sign
+#[derive(Debug, Clone)]
+pub struct PaymentAttempt {
+    pub method: String,
+    pub url: String,
+    pub host: String,
+    pub path: String,
+    /// Requirements of the payment option that was chosen
+    pub requirements: PaymentRequirements,
+    /// Exact amount in the asset's atomic units
+    pub amount: Amount,
+}
+
+impl PaymentAttempt {
+    /// Describe paying `requirements` for a request to `url`
+    pub fn new(
+        method: &str,
+        url: &str,
+        requirements: &PaymentRequirements,
+    ) -> Result<Self, X402Error> {
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| X402Error::InvalidInput(format!("Invalid request URL {}: {}", url, e)))?;
+        Ok(Self {
+            method: method.to_ascii_uppercase(),
+            url: url.to_string(),
+            host: parsed.host_str().unwrap_or_default().to_ascii_lowercase(),
+            path: parsed.path().to_string(),
+            requirements: requirements.clone(),
+            amount: requirements.amount()?,
+        })
+    }
+
+    /// Token mint being paid, or `None` for native SOL
+    pub fn asset(&self) -> Option<&str> {
+        self.requirements.asset.as_deref()
+    }
+}
+
+/// Answer of an [`ApprovalFn`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaymentApproval {
+    Approve,
+    Deny(String),
+}
+
+/// Future returned by an [`ApprovalFn`]
+pub type ApprovalFuture = Pin<Box<dyn Future<Output = PaymentApproval> + Send>>;
+
+/// Async hook that approves or denies individual payments, e.g. by asking a user
+#[derive(Clone)]
+pub struct ApprovalFn(Arc<dyn Fn(PaymentAttempt) -> ApprovalFuture + Send + Sync>);
+
+impl ApprovalFn {
+    /// Wrap an async approval closure
+    pub fn new<F, Fut>(f: F) -> Self
+    where
+        F: Fn(PaymentAttempt) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = PaymentApproval> + Send + 'static,
+    {
+        Self(Arc::new(move |attempt| Box::pin(f(attempt))))
+    }
+
+    /// Ask the hook about a payment
+    pub async fn approve(&self, attempt: PaymentAttempt) -> PaymentApproval {
+        (self.0)(attempt).await
+    }
+}
+
+impl fmt::Debug for ApprovalFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ApprovalFn")
+    }
+}
+
+/// Which [`SpendingPolicy`] rule blocked a payment
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyDenial {
+    /// The payment is larger than the per-payment cap for its asset
+    PerPaymentCap { asset: Option<String>, limit: u64, amount: u64 },
+    /// The payment would take the asset's spending over a rolling budget
+    Budget {
+        asset: Option<String>,
+        window: BudgetWindow,
+        limit: u64,
+        spent: u64,
+        amount: u64,
+    },
+    /// The host matches a deny rule
+    HostDenied { host: String, rule: String },
+    /// Hosts are allow-listed and this one is not
+    HostNotAllowed { host: String },
+    /// The request matches a deny route
+    RouteDenied { route: String, rule: String },
+    /// Routes are allow-listed and this one is not
+    RouteNotAllowed { route: String },
+    /// The approval hook refused the payment
+    Hook { reason: String },
+}
+
+impl PolicyDenial {
+    /// Stable machine-readable code
+    pub fn code(&self) -> &'static str {
+        match self {
+            PolicyDenial::PerPaymentCap { .. } => "per_payment_cap",
+            PolicyDenial::Budget { .. } => "budget_exceeded",
+            PolicyDenial::HostDenied { .. } => "host_denied",
+            PolicyDenial::HostNotAllowed { .. } => "host_not_allowed",
+            PolicyDenial::RouteDenied { .. } => "route_denied",
+            PolicyDenial::RouteNotAllowed { .. } => "route_not_allowed",
+            PolicyDenial::Hook { .. } => "denied_by_hook",
+        }
+    }
+}
+
+impl fmt::Display for PolicyDenial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sol = |asset: &Option<String>| asset.clone().unwrap_or_else(|| "SOL".to_string());
+        match self {
+            PolicyDenial::PerPaymentCap { asset, limit, amount } => write!(
+                f,
+                "{} exceeds the per-payment cap of {} for {}",
+                amount,
+                limit,
+                sol(asset)
+            ),
+            PolicyDenial::Budget { asset, window, limit, spent, amount } => write!(
+                f,
+                "{} would exceed the per-{} budget of {} for {} ({} already spent)",
+                amount,
+                window,
+                limit,
+                sol(asset),
+                spent
+            ),
+            PolicyDenial::HostDenied { host, rule } => {
+                write!(f, "Host {} is denied by {}", host, rule)
+            }
+            PolicyDenial::HostNotAllowed { host } => write!(f, "Host {} is not allowed", host),
+            PolicyDenial::RouteDenied { route, rule } => {
+                write!(f, "Route {} is denied by {}", route, rule)
+            }
+            PolicyDenial::RouteNotAllowed { route } => write!(f, "Route {} is not allowed", route),
+            PolicyDenial::Hook { reason } => write!(f, "Denied by approval hook: {}", reason),
+        }
+    }
+}
+
+/// Budget held for a payment until it is known whether the payment was sent
+#[derive(Debug)]
+#[must_use = "release the reservation if the payment is not sent"]
+pub struct SpendReservation(Option<u64>);
+
+#[derive(Debug)]
+struct Spend {
+    id: u64,
+    at: Instant,
+    asset: Option<String>,
+    amount: u64,
+}
+
+#[derive(Debug, Default)]
+struct SpendHistory {
+    next_id: u64,
+    spends: VecDeque<Spend>,
+}
+
+impl SpendHistory {
+    fn prune(&mut self, now: Instant) {
+        let oldest = BudgetWindow::Day.duration();
+        while matches!(self.spends.front(), Some(spend) if now.duration_since(spend.at) >= oldest) {
+            self.spends.pop_front();
+        }
+    }
+
+    fn spent(&self, asset: &Option<String>, window: BudgetWindow, now: Instant) -> u64 {
+        self.spends
+            .iter()
+            .filter(|spend| &spend.asset == asset && now.duration_since(spend.at) < window.duration())
+            .map(|spend| spend.amount)
+            .sum()
+    }
+}
+
+/// Client-side rules for which payments a [`Fetcher`](super::Fetcher) may make.
+///
+/// Caps and budgets are in atomic units and keyed by asset (`None` for SOL, or
+/// the token mint), so 100000 lamports and 100000 micro-USDC are tracked
+/// separately. Host rules are exact hostnames or `*.example.com` for any
+/// subdomain; route rules use [`RoutePattern`] keys such as `"GET /api/*"`.
+/// Deny rules win over allow rules, and an empty allow list allows everything.
+///
+/// Budgets are rolling: a payment counts against every window it was made in.
+/// Share one policy between fetchers with an `Arc` to give them one budget.
+///
+/// ```rust,ignore
+/// let usdc = "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU";
+/// let policy = SpendingPolicy::new()
+///     .max_per_payment(Some(usdc), 10_000)
+///     .budget(Some(usdc), BudgetWindow::Day, 1_000_000)
+///     .budget(None, BudgetWindow::Hour, 5_000_000)
+///     .allow_host("*.example.com")
+///     .deny_route("POST /admin/*")?;
+/// let fetcher = Fetcher::new(wallet, None).with_policy(Arc::new(policy));
+/// ```
+#[derive(Debug, Default)]
+pub struct SpendingPolicy {
+    per_payment: HashMap<Option<String>, u64>,
+    budgets: Vec<(Option<String>, BudgetWindow, u64)>,
+    allowed_hosts: Vec<String>,
+    denied_hosts: Vec<String>,
+    allowed_routes: Vec<(String, RoutePattern)>,
+    denied_routes: Vec<(String, RoutePattern)>,
+    approval: Option<ApprovalFn>,
+    history: Mutex<SpendHistory>,
+}
+
+/// Match a host against `example.com` or `*.example.com`
+fn host_matches(rule: &str, host: &str) -> bool {
+    match rule.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.')),
+        None => rule == host,
+    }
+}
+
+impl SpendingPolicy {
+    /// A policy that allows every payment
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cap single payments in `asset` (`None` for SOL) at `limit` atomic units
+    pub fn max_per_payment(mut self, asset: Option<&str>, limit: u64) -> Self {
+        self.per_payment.insert(asset.map(str::to_string), limit);
+        self
+    }
+
+    /// Limit total spending in `asset` over a rolling `window` to `limit` atomic units
+    pub fn budget(mut self, asset: Option<&str>, window: BudgetWindow, limit: u64) -> Self {
+        let asset = asset.map(str::to_string);
+        self.budgets.retain(|(a, w, _)| !(a == &asset && *w == window));
+        self.budgets.push((asset, window, limit));
+        self
+    }
+
+    /// Only pay hosts matching one of the allow rules
+    pub fn allow_host(mut self, host: impl Into<String>) -> Self {
+        self.allowed_hosts.push(host.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Never pay hosts matching this rule
+    pub fn deny_host(mut self, host: impl Into<String>) -> Self {
+        self.denied_hosts.push(host.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Only pay for requests matching one of the allowed routes, e.g. `"GET /api/*"`
+    pub fn allow_route(mut self, route: &str) -> Result<Self, X402Error> {
+        self.allowed_routes.push((route.to_string(), RoutePattern::parse(route)?));
+        Ok(self)
+    }
+
+    /// Never pay for requests matching this route
+    pub fn deny_route(mut self, route: &str) -> Result<Self, X402Error> {
+        self.denied_routes.push((route.to_string(), RoutePattern::parse(route)?));
+        Ok(self)
+    }
+
+    /// Ask `hook` about every payment that passes the other rules
+    pub fn with_approval(mut self, hook: ApprovalFn) -> Self {
+        self.approval = Some(hook);
+        self
+    }
+
+    /// The spend history, recovered if a thread panicked while holding the lock;
+    /// every update leaves it consistent
+    fn history(&self) -> MutexGuard<'_, SpendHistory> {
+        self.history.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Atomic units of `asset` spent during the current `window`
+    pub fn spent(&self, asset: Option<&str>, window: BudgetWindow) -> u64 {
+        let history = self.history();
+        history.spent(&asset.map(str::to_string), window, Instant::now())
+    }
+
+    /// Check a payment against the static rules, without the hook or budgets
+    pub fn check(&self, attempt: &PaymentAttempt) -> Result<(), PolicyDenial> {
+        if let Some(rule) = self.denied_hosts.iter().find(|rule| host_matches(rule, &attempt.host)) {
+            return Err(PolicyDenial::HostDenied {
+                host: attempt.host.clone(),
+                rule: rule.clone(),
+            });
+        }
+        if !self.allowed_hosts.is_empty()
+            && !self.allowed_hosts.iter().any(|rule| host_matches(rule, &attempt.host))
+        {
+            return Err(PolicyDenial::HostNotAllowed {
+                host: attempt.host.clone(),
+            });
+        }
+
+        let route = format!("{} {}", attempt.method, attempt.path);
+        let matches = |(_, pattern): &&(String, RoutePattern)| {
+            pattern.matches(&attempt.method, &attempt.path).is_some()
+        };
+        if let Some((rule, _)) = self.denied_routes.iter().find(matches) {
+            return Err(PolicyDenial::RouteDenied {
+                route,
+                rule: rule.clone(),
+            });
+        }
+        if !self.allowed_routes.is_empty() && !self.allowed_routes.iter().any(|r| matches(&r)) {
+            return Err(PolicyDenial::RouteNotAllowed { route });
+        }
+
+        let asset = attempt.requirements.asset.clone();
+        if let Some(&limit) = self.per_payment.get(&asset) {
+            if attempt.amount.atomic() > limit {
+                return Err(PolicyDenial::PerPaymentCap {
+                    asset,
+                    limit,
+                    amount: attempt.amount.atomic(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a payment fits in the budgets right now, without reserving it
+    pub fn check_budgets(&self, attempt: &PaymentAttempt) -> Result<(), PolicyDenial> {
+        let history = self.history();
+        self.budget_denial(&history, attempt, Instant::now())
+    }
+
+    fn budget_denial(
+        &self,
+        history: &SpendHistory,
+        attempt: &PaymentAttempt,
+        now: Instant,
+    ) -> Result<(), PolicyDenial> {
+        let asset = &attempt.requirements.asset;
+        let amount = attempt.amount.atomic();
+        for (budget_asset, window, limit) in &self.budgets {
+            if budget_asset != asset {
+                continue;
+            }
+            let spent = history.spent(asset, *window, now);
+            if spent.saturating_add(amount) > *limit {
+                return Err(PolicyDenial::Budget {
+                    asset: asset.clone(),
+                    window: *window,
+                    limit: *limit,
+                    spent,
+                    amount,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Run every rule and, if the payment is allowed, reserve it against the budgets.
+    ///
+    /// The reservation counts as spent; [`release`](Self::release) it if the
+    /// payment ends up not being sent.
+    pub async fn authorize(&self, attempt: &PaymentAttempt) -> Result<SpendReservation, X402Error> {
+        self.check(attempt).map_err(X402Error::PolicyDenied)?;
+
+        if let Some(hook) = &self.approval {
+            if let PaymentApproval::Deny(reason) = hook.approve(attempt.clone()).await {
+                return Err(X402Error::PolicyDenied(PolicyDenial::Hook { reason }));
+            }
+        }
+
+        if self.budgets.is_empty() {
+            return Ok(SpendReservation(None));
+        }
+
+        // Check and record under one lock so concurrent payments cannot overspend
+        let asset = attempt.requirements.asset.clone();
+        let amount = attempt.amount.atomic();
+        let now = Instant::now();
+        let mut history = self.history();
+        history.prune(now);
+        self.budget_denial(&history, attempt, now)
+            .map_err(X402Error::PolicyDenied)?;
+
+        let id = history.next_id;
+        history.next_id += 1;
+        history.spends.push_back(Spend {
+            id,
+            at: now,
+            asset,
+            amount,
+        });
+        Ok(SpendReservation(Some(id)))
+    }
+
+    /// Return a reserved payment to the budgets
+    pub fn release(&self, reservation: SpendReservation) {
+        if let Some(id) = reservation.0 {
+            self.history().spends.retain(|spend| spend.id != id);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::error::X402Error;
+use crate::types::{SettleResponse, X402Response};
+use bytes::Bytes;
+use futures_util::{Stream, TryStreamExt};
+use std::collections::HashMap;
+
+/// Response to a [`Fetcher`](super::Fetcher) request whose body has not been read yet.
+///
+/// Returned by [`Fetcher::fetch_stream`](super::Fetcher::fetch_stream) for large
+/// or long-running responses, e.g. downloads or server-sent events. The status,
+/// headers and settlement receipt are available right away.
+#[derive(Debug)]
+pub struct X402StreamResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    /// Settlement receipt parsed from the `X-PAYMENT-RESPONSE` header, if any.
+    /// A malformed receipt is left out here but kept in `headers`.
+    pub payment_response: Option<SettleResponse>,
+    response: reqwest::Response,
+}
+
+impl X402StreamResponse {
+    /// Read the status, headers and receipt of a response, leaving the body unread
+    pub(crate) fn new(response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let mut headers = HashMap::new();
+        for (key, value) in response.headers() {
+            if let Ok(val_str) = value.to_str() {
+                headers.insert(key.to_string(), val_str.to_string());
+            }
+        }
+        let payment_response = headers
+            .get("x-payment-response")
+            .and_then(|value| SettleResponse::from_header(value).ok());
+
+        Self {
+            status,
+            headers,
+            payment_response,
+            response,
+        }
+    }
+
+    /// Next chunk of the body, or `None` at its end
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, X402Error> {
+        Ok(self.response.chunk().await?)
+    }
+
+    /// The body as a stream of chunks
+    pub fn bytes_stream(self) -> impl Stream<Item = Result<Bytes, X402Error>> {
+        self.response.bytes_stream().map_err(X402Error::from)
+    }
+
+    /// Read the whole body into an [`X402Response`]
+    pub async fn into_response(self) -> Result<X402Response, X402Error> {
+        let body = self.response.bytes().await?.to_vec();
+        Ok(X402Response {
+            status: self.status,
+            headers: self.headers,
+            body,
+            payment_response: self.payment_response,
+        })
+    }
+
+    /// The underlying reqwest response
+    pub fn into_inner(self) -> reqwest::Response {
+        self.response
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+/// How [`Fetcher`](super::Fetcher) retries a paid request that did not succeed.
+///
+/// Every retry first asks whether the payment could have settled:
+///
+/// - The server may have received the payment if the connection broke after the
+///   request was sent, or if it answered with a 5xx. In that case the fetcher
+///   resends the same signed payload. A transaction can land only once, so this
+///   cannot charge twice. It never signs a new payment after this point, and if
+///   retries run out it returns [`X402Error::PaymentMayHaveSettled`].
+/// - The payment was never submitted if the connection could not be opened;
+///   the fetcher resends it, and if retries run out it returns
+///   [`X402Error::PaymentNotSubmitted`].
+/// - The server refused the payment if it answers with another 402, for
+///   example because the blockhash expired or the price changed. If
+///   `renegotiate` is set, the fetcher signs a new payment with a fresh
+///   blockhash against the requirements in that 402.
+///
+/// The default policy never retries.
+///
+/// [`X402Error::PaymentMayHaveSettled`]: crate::error::X402Error::PaymentMayHaveSettled
+/// [`X402Error::PaymentNotSubmitted`]: crate::error::X402Error::PaymentNotSubmitted
+///
+/// ```rust,ignore
+/// let fetcher = Fetcher::new(wallet, None).with_retry(RetryPolicy::new(3));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first paid attempt
+    pub max_retries: u32,
+    /// Delay before the first retry; it doubles for each following retry
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between retries
+    pub max_backoff: Duration,
+    /// Treat a 5xx answer to a paid request as "may have settled" and resend the
+    /// payment; when off, the 5xx response is returned to the caller
+    pub retry_server_errors: bool,
+    /// Sign a new payment when the server refuses the previous one with a 402
+    pub renegotiate: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry: one paid attempt, and a 5xx answer is returned as is
+    pub fn none() -> Self {
+        Self {
+            retry_server_errors: false,
+            renegotiate: false,
+            ..Self::new(0)
+        }
+    }
+
+    /// Retry up to `max_retries` times, backing off from 200ms up to 5s
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            retry_server_errors: true,
+            renegotiate: true,
+        }
+    }
+
+    /// Set the initial and maximum delay between retries
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Delay before retry number `retry` (starting at 0)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
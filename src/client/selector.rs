@@ -0,0 +1,327 @@
+use super::policy::{PaymentAttempt, PolicyDenial, SpendingPolicy};
+use crate::error::X402Error;
+use crate::solana::{AsyncTransactionBuilder, SolanaRpc};
+use crate::types::{Network, PaymentRequirements};
+use async_trait::async_trait;
+use solana_sdk::pubkey::Pubkey;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// What a [`RequirementsSelector`] knows about the payment it is choosing
+pub struct SelectionContext<'a> {
+    pub method: &'a str,
+    pub url: &'a str,
+    /// Address that will sign and fund the payment
+    pub payer: Pubkey,
+    /// RPC backend the payment will be built with
+    pub rpc: Arc<dyn SolanaRpc>,
+    /// The fetcher's `max_value`, in atomic units of whichever asset is paid
+    pub max_value: Option<u64>,
+    /// The fetcher's spending policy
+    pub policy: Option<&'a SpendingPolicy>,
+}
+
+/// Picks which of the payment options in a 402 response to pay.
+///
+/// Without a selector the [`Fetcher`](super::Fetcher) pays the first option.
+#[async_trait]
+pub trait RequirementsSelector: Send + Sync {
+    /// Choose one of `options`, or return
+    /// [`X402Error::NoAcceptablePaymentOption`] explaining why none fits
+    async fn select(
+        &self,
+        options: &[PaymentRequirements],
+        context: &SelectionContext<'_>,
+    ) -> Result<PaymentRequirements, X402Error>;
+}
+
+/// Why a [`RequirementsSelector`] passed over a payment option
+#[derive(Debug, Clone, PartialEq)]
+pub enum RejectionReason {
+    /// The option is on a network the selector does not pay on
+    NetworkNotAccepted(Network),
+    /// The asset is not one of the selector's preferred assets
+    AssetNotAllowed,
+    /// The amount, token metadata or an address could not be parsed
+    InvalidRequirements(String),
+    /// The amount is above the fetcher's `max_value`
+    ExceedsMaxValue { max: u64, amount: u64 },
+    /// The spending policy would refuse the payment
+    Policy(PolicyDenial),
+    /// The wallet holds less than the payment needs; `asset` is `None` for SOL
+    InsufficientBalance {
+        asset: Option<String>,
+        required: u64,
+        available: u64,
+    },
+    /// The balance could not be read
+    BalanceUnavailable(String),
+}
+
+impl fmt::Display for RejectionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectionReason::NetworkNotAccepted(network) => {
+                write!(f, "network {:?} is not accepted", network)
+            }
+            RejectionReason::AssetNotAllowed => f.write_str("asset is not a preferred asset"),
+            RejectionReason::InvalidRequirements(msg) => write!(f, "invalid requirements: {}", msg),
+            RejectionReason::ExceedsMaxValue { max, amount } => {
+                write!(f, "{} exceeds the max value of {}", amount, max)
+            }
+            RejectionReason::Policy(denial) => write!(f, "{}", denial),
+            RejectionReason::InsufficientBalance { asset, required, available } => write!(
+                f,
+                "needs {} {} but the wallet holds {}",
+                required,
+                asset.as_deref().unwrap_or("lamports"),
+                available
+            ),
+            RejectionReason::BalanceUnavailable(msg) => {
+                write!(f, "balance unavailable: {}", msg)
+            }
+        }
+    }
+}
+
+/// A payment option a [`RequirementsSelector`] did not pick, and why
+#[derive(Debug, Clone)]
+pub struct RejectedOption {
+    pub requirements: PaymentRequirements,
+    pub reason: RejectionReason,
+}
+
+impl fmt::Display for RejectedOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} on {:?}: {}",
+            self.requirements.max_amount_required,
+            self.requirements.asset.as_deref().unwrap_or("SOL"),
+            self.requirements.network,
+            self.reason
+        )
+    }
+}
+
+/// [`RequirementsSelector`] that pays with the cheapest option the wallet can afford.
+///
+/// Options are dropped if they are on another network, exceed `max_value` or
+/// the spending policy's caps and budgets, or need more than the wallet holds
+/// (including the network fee, unless the facilitator pays it, and the rent of
+/// token accounts the payment creates). Of the
+/// remaining options, those with a [`rate`](Self::rate) are ranked by their
+/// value; the others follow in order of preference, then in the server's
+/// order. Options in the same asset are always ranked by amount, cheapest
+/// first, whether or not the asset has a rate.
+///
+/// ```rust,ignore
+/// let usdc = "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU";
+/// let selector = BalanceAwareSelector::new()
+///     .network(Network::SolanaDevnet)
+///     .prefer(Some(usdc))
+///     .prefer(None)
+///     .rate(Some(usdc), 1.0)
+///     .rate(None, 150.0);
+/// let fetcher = Fetcher::new(wallet, None).with_selector(Arc::new(selector));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BalanceAwareSelector {
+    network: Option<Network>,
+    preferred: Vec<Option<String>>,
+    only_preferred: bool,
+    rates: HashMap<Option<String>, f64>,
+    check_balances: bool,
+}
+
+impl Default for BalanceAwareSelector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BalanceAwareSelector {
+    /// Select among every offered option, checking balances
+    pub fn new() -> Self {
+        Self {
+            network: None,
+            preferred: Vec::new(),
+            only_preferred: false,
+            rates: HashMap::new(),
+            check_balances: true,
+        }
+    }
+
+    /// Only pay on `network`
+    pub fn network(mut self, network: Network) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// Prefer `asset` (`None` for SOL) over assets added after it
+    pub fn prefer(mut self, asset: Option<&str>) -> Self {
+        let asset = asset.map(str::to_string);
+        if !self.preferred.contains(&asset) {
+            self.preferred.push(asset);
+        }
+        self
+    }
+
+    /// Never pay in assets that were not passed to [`prefer`](Self::prefer)
+    pub fn only_preferred(mut self) -> Self {
+        self.only_preferred = true;
+        self
+    }
+
+    /// Value of one whole unit of `asset` (e.g. in USD), used to compare prices
+    /// across assets
+    pub fn rate(mut self, asset: Option<&str>, value_per_unit: f64) -> Self {
+        self.rates.insert(asset.map(str::to_string), value_per_unit);
+        self
+    }
+
+    /// Do not query balances, e.g. when the wallet is funded just in time
+    pub fn skip_balance_checks(mut self) -> Self {
+        self.check_balances = false;
+        self
+    }
+
+    /// Why `requirements` cannot be paid, or its atomic amount and value if it can
+    async fn evaluate(
+        &self,
+        requirements: &PaymentRequirements,
+        context: &SelectionContext<'_>,
+    ) -> Result<(u64, Option<f64>), RejectionReason> {
+        if let Some(network) = &self.network {
+            if requirements.network != *network {
+                return Err(RejectionReason::NetworkNotAccepted(requirements.network.clone()));
+            }
+        }
+        if self.only_preferred && !self.preferred.contains(&requirements.asset) {
+            return Err(RejectionReason::AssetNotAllowed);
+        }
+        let amount = requirements
+            .amount()
+            .map_err(|e| RejectionReason::InvalidRequirements(e.to_string()))?;
+        if let Some(max) = context.max_value {
+            if amount.atomic() > max {
+                return Err(RejectionReason::ExceedsMaxValue {
+                    max,
+                    amount: amount.atomic(),
+                });
+            }
+        }
+        if let Some(policy) = context.policy {
+            let attempt = PaymentAttempt::new(context.method, context.url, requirements)
+                .map_err(|e| RejectionReason::InvalidRequirements(e.to_string()))?;
+            policy.check(&attempt).map_err(RejectionReason::Policy)?;
+            policy.check_budgets(&attempt).map_err(RejectionReason::Policy)?;
+        }
+        if self.check_balances {
+            self.check_balance(requirements, amount.atomic(), context).await?;
+        }
+
+        let value = self.rates.get(&requirements.asset).map(|rate| {
+            amount.atomic() as f64 / 10f64.powi(amount.decimals() as i32) * rate
+        });
+        Ok((amount.atomic(), value))
+    }
+
+    /// Whether the payer can fund the option, using the same preflight checks
+    /// as the [`Fetcher`](super::Fetcher)
+    async fn check_balance(
+        &self,
+        requirements: &PaymentRequirements,
+        amount: u64,
+        context: &SelectionContext<'_>,
+    ) -> Result<(), RejectionReason> {
+        let address = |address: &str| {
+            address
+                .parse::<Pubkey>()
+                .map_err(|e| RejectionReason::InvalidRequirements(format!("Invalid address {}: {}", address, e)))
+        };
+        let mut builder = AsyncTransactionBuilder::from_rpc(context.rpc.clone());
+        if let Some(fee_payer) = requirements.fee_payer() {
+            builder = builder.with_fee_payer(address(fee_payer)?);
+        }
+
+        let result = match &requirements.asset {
+            Some(mint) => {
+                builder
+                    .check_spl_token_payment_funds(
+                        &context.payer,
+                        &address(&requirements.pay_to)?,
+                        &address(mint)?,
+                        amount,
+                        requirements.token_decimals().unwrap_or(9),
+                    )
+                    .await
+            }
+            None => {
+                builder
+                    .check_payment_funds(&context.payer, &address(&requirements.pay_to)?, amount)
+                    .await
+            }
+        };
+        result.map_err(|e| match e {
+            X402Error::InsufficientFunds { asset, required, available } => {
+                RejectionReason::InsufficientBalance { asset, required, available }
+            }
+            e => RejectionReason::BalanceUnavailable(e.to_string()),
+        })
+    }
+}
+
+#[async_trait]
+impl RequirementsSelector for BalanceAwareSelector {
+    async fn select(
+        &self,
+        options: &[PaymentRequirements],
+        context: &SelectionContext<'_>,
+    ) -> Result<PaymentRequirements, X402Error> {
+        let mut acceptable = Vec::new();
+        let mut rejected = Vec::new();
+        for (index, requirements) in options.iter().enumerate() {
+            match self.evaluate(requirements, context).await {
+                Ok((amount, value)) => acceptable.push((value, amount, index)),
+                Err(reason) => rejected.push(RejectedOption {
+                    requirements: requirements.clone(),
+                    reason,
+                }),
+            }
+        }
+
+        // Priced options first, cheapest first; the same asset by amount; then by
+        // preference and offer order
+        let preference = |index: usize| {
+            self.preferred
+                .iter()
+                .position(|asset| *asset == options[index].asset)
+                .unwrap_or(self.preferred.len())
+        };
+        acceptable.sort_by(|(a_value, a_amount, a), (b_value, b_amount, b)| {
+            let by_value = match (a_value, b_value) {
+                (Some(a_value), Some(b_value)) => a_value.total_cmp(b_value),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            };
+            let by_amount = if options[*a].asset == options[*b].asset {
+                a_amount.cmp(b_amount)
+            } else {
+                Ordering::Equal
+            };
+            by_value
+                .then(by_amount)
+                .then(preference(*a).cmp(&preference(*b)))
+                .then(a.cmp(b))
+        });
+
+        match acceptable.first() {
+            Some((_, _, index)) => Ok(options[*index].clone()),
+            None => Err(X402Error::NoAcceptablePaymentOption(rejected)),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::client::{PolicyDenial, RejectedOption};
 use std::fmt;
 
 #[derive(Debug)]
@@ -10,10 +11,24 @@ pub enum X402Error {
     PaymentRequired(String),
     PaymentVerificationFailed(String),
     PaymentAmountExceeded { expected: u64, got: u64 },
+    /// The payer cannot fund a payment; `asset` is the token mint, or `None` for
+    /// lamports (amount, fees and rent)
+    InsufficientFunds { asset: Option<String>, required: u64, available: u64 },
+    /// None of the offered payment options could be paid, with the reason for each
+    NoAcceptablePaymentOption(Vec<RejectedOption>),
+    /// The client's spending policy refused to make a payment
+    PolicyDenied(PolicyDenial),
+    /// A payment was signed but never reached the server; no money moved
+    PaymentNotSubmitted(String),
+    /// A payment reached the server, or may have, without a usable response;
+    /// `signature` identifies the transaction when it is known before settlement
+    PaymentMayHaveSettled { signature: Option<String>, reason: String },
     InvalidSignature(String),
+    InvalidNonce(String),
     SerializationError(String),
     DeserializationError(String),
     SolanaError(String),
+    WalletError(String),
     HttpError(String),
     NotImplemented(String),
 }
@@ -33,10 +48,39 @@ impl fmt::Display for X402Error {
             X402Error::PaymentAmountExceeded { expected, got } => {
                 write!(f, "Payment amount exceeded: expected {}, got {}", expected, got)
             }
+            X402Error::InsufficientFunds { asset, required, available } => write!(
+                f,
+                "Insufficient funds: {} {} required, {} available",
+                required,
+                asset.as_deref().unwrap_or("lamports"),
+                available
+            ),
+            X402Error::NoAcceptablePaymentOption(rejected) => {
+                if rejected.is_empty() {
+                    return write!(f, "No acceptable payment option: none offered");
+                }
+                write!(f, "No acceptable payment option:")?;
+                for option in rejected {
+                    write!(f, "\n  - {}", option)?;
+                }
+                Ok(())
+            }
+            X402Error::PolicyDenied(denial) => {
+                write!(f, "Payment denied by spending policy: {}", denial)
+            }
+            X402Error::PaymentNotSubmitted(msg) => write!(f, "Payment not submitted: {}", msg),
+            X402Error::PaymentMayHaveSettled { signature, reason } => match signature {
+                Some(signature) => {
+                    write!(f, "Payment {} may have settled: {}", signature, reason)
+                }
+                None => write!(f, "Payment may have settled: {}", reason),
+            },
             X402Error::InvalidSignature(msg) => write!(f, "Invalid signature: {}", msg),
+            X402Error::InvalidNonce(msg) => write!(f, "Invalid nonce: {}", msg),
             X402Error::SerializationError(msg) => write!(f, "Serialization error: {}", msg),
             X402Error::DeserializationError(msg) => write!(f, "Deserialization error: {}", msg),
             X402Error::SolanaError(msg) => write!(f, "Solana error: {}", msg),
+            X402Error::WalletError(msg) => write!(f, "Wallet error: {}", msg),
             X402Error::HttpError(msg) => write!(f, "HTTP error: {}", msg),
             X402Error::NotImplemented(msg) => write!(f, "Not implemented: {}", msg),
         }
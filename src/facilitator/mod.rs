@@ -1,3 +1,10 @@
 mod handler;
+pub mod server;
+mod verifier;
 
-pub use handler::Handler;
\ No newline at end of file
+pub use handler::Handler;
+pub use server::FacilitatorService;
+pub use verifier::{
+    simulation_failure, verify_payment, InvalidPaymentReason, VerifiedTransfer,
+    MAX_COMPUTE_UNIT_PRICE,
+};
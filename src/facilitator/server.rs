@@ -0,0 +1,384 @@
+use super::verifier::{simulation_failure, verify_payment, InvalidPaymentReason};
+use crate::error::X402Error;
+use crate::solana::{SolanaRpc, TransactionBuilder, Wallet};
+use crate::types::{
+    Network, PaymentPayload, PaymentRequirements, PaymentScheme, SettleResponse,
+    SupportedPaymentKind, SupportedPaymentKindsResponse, VerifyResponse,
+};
+use actix_web::{web, HttpResponse};
+#[cfg(feature = "axum")]
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+
+/// Body of a `POST /verify` request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyRequest {
+    pub payment_payload: PaymentPayload,
+    pub payment_requirements: PaymentRequirements,
+}
+
+/// Body of a `POST /settle` request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettleRequest {
+    pub payment_payload: PaymentPayload,
+    pub payment_requirements: PaymentRequirements,
+}
+
+/// Facilitator that verifies and settles x402 payments for one Solana network.
+///
+/// Mount it on an actix `App` with [`configure`] or build an axum router with
+/// [`router`] (`axum` feature) to expose `/verify`, `/settle` and `/supported`.
+pub struct FacilitatorService {
+    network: Network,
+    rpc_url: String,
+    rpc: Arc<dyn SolanaRpc>,
+    wallet: Option<Wallet>,
+    simulate: bool,
+}
+
+impl FacilitatorService {
+    /// Create a facilitator for `network` using its default public RPC endpoint
+    pub fn new(network: Network) -> Self {
+        let rpc_url = network.default_rpc_url().to_string();
+        Self {
+            rpc: Arc::new(RpcClient::new(rpc_url.clone())),
+            rpc_url,
+            network,
+            wallet: None,
+            simulate: true,
+        }
+    }
+
+    /// Use a custom RPC endpoint
+    pub fn with_rpc_url(mut self, rpc_url: impl Into<String>) -> Self {
+        self.rpc_url = rpc_url.into();
+        self.rpc = Arc::new(RpcClient::new(self.rpc_url.clone()));
+        self
+    }
+
+    /// Use a custom RPC backend, such as an [`InMemoryLedger`](crate::solana::InMemoryLedger)
+    pub fn with_rpc(mut self, rpc: Arc<dyn SolanaRpc>) -> Self {
+        self.rpc = rpc;
+        self
+    }
+
+    /// Attach the facilitator's own wallet.
+    ///
+    /// The wallet is advertised as `feePayer` in `/supported` and co-signs
+    /// payments that name it as their fee payer when they are settled.
+    pub fn with_wallet(mut self, wallet: Wallet) -> Self {
+        self.wallet = Some(wallet);
+        self
+    }
+
+    /// Enable or disable simulating payments during verification (on by default)
+    pub fn with_simulation(mut self, enabled: bool) -> Self {
+        self.simulate = enabled;
+        self
+    }
+
+    /// Network this facilitator serves
+    pub fn network(&self) -> &Network {
+        &self.network
+    }
+
+    /// RPC endpoint used to reach the network
+    pub fn rpc_url(&self) -> &str {
+        &self.rpc_url
+    }
+
+    /// Facilitator wallet, if configured
+    pub fn wallet(&self) -> Option<&Wallet> {
+        self.wallet.as_ref()
+    }
+
+    /// Account that pays network fees for payments, if this facilitator sponsors them
+    pub fn fee_payer(&self) -> Option<Pubkey> {
+        self.wallet.as_ref().map(Wallet::public_key)
+    }
+
+    /// Payment kinds accepted by this facilitator
+    pub fn supported(&self) -> SupportedPaymentKindsResponse {
+        SupportedPaymentKindsResponse {
+            kinds: vec![SupportedPaymentKind {
+                x402_version: 1,
+                scheme: PaymentScheme::Exact,
+                network: self.network.clone(),
+                extra: self
+                    .fee_payer()
+                    .map(|fee_payer| json!({ "feePayer": fee_payer.to_string() })),
+            }],
+        }
+    }
+
+    /// Verify a payment transaction against its requirements.
+    ///
+    /// A payment that does not satisfy the requirements is not an error: the
+    /// response has `verified: false` and the reason in `invalid_reason`.
+    pub async fn verify(
+        &self,
+        payment_payload: &PaymentPayload,
+        payment_requirements: &PaymentRequirements,
+    ) -> Result<VerifyResponse, X402Error> {
+        // 1. Only payments on our own network can be settled here
+        let fee_payer = self.fee_payer().map(|fee_payer| fee_payer.to_string());
+        let result = if payment_requirements.network != self.network {
+            Err(InvalidPaymentReason::NetworkMismatch {
+                expected: self.network.clone(),
+                got: payment_requirements.network.clone(),
+            })
+        } else if let Some(requested) = payment_requirements
+            .fee_payer()
+            .filter(|requested| Some(*requested) != fee_payer.as_deref())
+        {
+            // We can only co-sign for our own wallet
+            Err(InvalidPaymentReason::FeePayerMismatch {
+                expected: fee_payer,
+                got: requested.to_string(),
+            })
+        } else {
+            // 2. Decode the transaction and check it pays what was required
+            verify_payment(payment_payload, payment_requirements)
+        };
+
+        // 3. Ask the chain whether the payment would actually succeed
+        let result = match result {
+            Ok(transfer) if self.simulate => match self.simulate(payment_payload).await? {
+                Some(reason) => Err(reason),
+                None => Ok(transfer),
+            },
+            result => result,
+        };
+
+        Ok(match result {
+            Ok(transfer) => VerifyResponse {
+                verified: true,
+                message: Some("Payment verified successfully".to_string()),
+                invalid_reason: None,
+                payer: Some(transfer.payer.to_string()),
+            },
+            Err(reason) => VerifyResponse {
+                verified: false,
+                message: Some(reason.to_string()),
+                invalid_reason: Some(reason.code().to_string()),
+                payer: None,
+            },
+        })
+    }
+
+    /// Simulate the payment transaction, returning why it would fail, if it would
+    async fn simulate(
+        &self,
+        payment_payload: &PaymentPayload,
+    ) -> Result<Option<InvalidPaymentReason>, X402Error> {
+        let transaction =
+            TransactionBuilder::deserialize_transaction(&payment_payload.signed_transaction)?;
+        // Signatures were already checked while decoding the payment
+        let simulation = self.rpc.simulate_transaction(&transaction).await?;
+
+        Ok(simulation
+            .err
+            .map(|error| simulation_failure(&transaction, error, simulation.logs)))
+    }
+
+    /// Verify a payment and submit its transaction to the network
+    pub async fn settle(
+        &self,
+        payment_payload: &PaymentPayload,
+        payment_requirements: &PaymentRequirements,
+    ) -> Result<SettleResponse, X402Error> {
+        // 1. First verify the transaction
+        let verification = self.verify(payment_payload, payment_requirements).await?;
+        if !verification.verified {
+            return Err(X402Error::PaymentVerificationFailed(
+                verification.message.unwrap_or_default(),
+            ));
+        }
+
+        // 2. Deserialize the transaction
+        let mut transaction =
+            TransactionBuilder::deserialize_transaction(&payment_payload.signed_transaction)?;
+
+        // In fee-payer mode, add our signature as the fee payer
+        if let Some(wallet) = &self.wallet {
+            if transaction.message.account_keys.first() == Some(&wallet.public_key()) {
+                let recent_blockhash = transaction.message.recent_blockhash;
+                transaction.try_partial_sign(&[wallet.keypair()], recent_blockhash)?;
+            }
+        }
+
+        // 3. Submit the transaction; the client already signed it with a recent blockhash
+        let signature = self.rpc.send_and_confirm_transaction(&transaction).await?;
+
+        Ok(SettleResponse {
+            signature: signature.to_string(),
+            settled: true,
+            network: Some(payment_requirements.network.clone()),
+            payer: verification.payer,
+            error_reason: None,
+            message: Some("Payment settled successfully".to_string()),
+        })
+    }
+}
+
+/// HTTP status for a verify or settle error: 400 when the request or payment
+/// is invalid, 502 when the RPC node failed, 500 for anything else on our side
+fn error_status(error: &X402Error) -> u16 {
+    match error {
+        X402Error::InvalidInput(_)
+        | X402Error::PaymentVerificationFailed(_)
+        | X402Error::PaymentAmountExceeded { .. }
+        | X402Error::InvalidSignature(_)
+        | X402Error::InvalidNonce(_)
+        | X402Error::DeserializationError(_) => 400,
+        X402Error::SolanaError(_) | X402Error::NetworkError(_) | X402Error::HttpError(_) => 502,
+        _ => 500,
+    }
+}
+
+/// Description returned by `GET /verify` and `GET /settle`
+fn endpoint_info(endpoint: &str, action: &str) -> serde_json::Value {
+    json!({
+        "endpoint": endpoint,
+        "description": format!("POST to {} x402 payments", action),
+        "body": {
+            "paymentPayload": "PaymentPayload",
+            "paymentRequirements": "PaymentRequirements"
+        }
+    })
+}
+
+/// Mount the facilitator routes on an actix `App` or scope.
+///
+/// ```rust,ignore
+/// let service = Arc::new(FacilitatorService::new(Network::SolanaDevnet));
+/// HttpServer::new(move || App::new().configure(facilitator::server::configure(service.clone())))
+/// ```
+pub fn configure(service: Arc<FacilitatorService>) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        cfg.app_data(web::Data::from(service))
+            .route("/supported", web::get().to(actix_supported))
+            .route("/verify", web::get().to(actix_verify_info))
+            .route("/verify", web::post().to(actix_verify))
+            .route("/settle", web::get().to(actix_settle_info))
+            .route("/settle", web::post().to(actix_settle));
+    }
+}
+
+async fn actix_verify_info() -> HttpResponse {
+    HttpResponse::Ok().json(endpoint_info("/verify", "verify"))
+}
+
+async fn actix_settle_info() -> HttpResponse {
+    HttpResponse::Ok().json(endpoint_info("/settle", "settle"))
+}
+
+async fn actix_supported(service: web::Data<FacilitatorService>) -> HttpResponse {
+    HttpResponse::Ok().json(service.supported())
+}
+
+async fn actix_verify(
+    service: web::Data<FacilitatorService>,
+    request: web::Json<VerifyRequest>,
+) -> HttpResponse {
+    match service
+        .verify(&request.payment_payload, &request.payment_requirements)
+        .await
+    {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => HttpResponse::build(
+            actix_web::http::StatusCode::from_u16(error_status(&e))
+                .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR),
+        )
+        .json(json!({
+            "error": format!("Verification failed: {}", e)
+        })),
+    }
+}
+
+async fn actix_settle(
+    service: web::Data<FacilitatorService>,
+    request: web::Json<SettleRequest>,
+) -> HttpResponse {
+    match service
+        .settle(&request.payment_payload, &request.payment_requirements)
+        .await
+    {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => HttpResponse::build(
+            actix_web::http::StatusCode::from_u16(error_status(&e))
+                .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR),
+        )
+        .json(json!({
+            "error": format!("Settlement failed: {}", e)
+        })),
+    }
+}
+
+/// Build an axum router exposing the facilitator routes
+#[cfg(feature = "axum")]
+pub fn router(service: Arc<FacilitatorService>) -> axum::Router {
+    use axum::routing::get;
+
+    axum::Router::new()
+        .route("/supported", get(axum_supported))
+        .route(
+            "/verify",
+            get(|| async { axum::Json(endpoint_info("/verify", "verify")) }).post(axum_verify),
+        )
+        .route(
+            "/settle",
+            get(|| async { axum::Json(endpoint_info("/settle", "settle")) }).post(axum_settle),
+        )
+        .with_state(service)
+}
+
+#[cfg(feature = "axum")]
+type AxumState = axum::extract::State<Arc<FacilitatorService>>;
+
+#[cfg(feature = "axum")]
+async fn axum_supported(
+    axum::extract::State(service): AxumState,
+) -> axum::Json<SupportedPaymentKindsResponse> {
+    axum::Json(service.supported())
+}
+
+#[cfg(feature = "axum")]
+async fn axum_verify(
+    axum::extract::State(service): AxumState,
+    axum::Json(request): axum::Json<VerifyRequest>,
+) -> (StatusCode, axum::Json<serde_json::Value>) {
+    match service
+        .verify(&request.payment_payload, &request.payment_requirements)
+        .await
+    {
+        Ok(response) => (StatusCode::OK, axum::Json(json!(response))),
+        Err(e) => (
+            StatusCode::from_u16(error_status(&e)).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            axum::Json(json!({ "error": format!("Verification failed: {}", e) })),
+        ),
+    }
+}
+
+#[cfg(feature = "axum")]
+async fn axum_settle(
+    axum::extract::State(service): AxumState,
+    axum::Json(request): axum::Json<SettleRequest>,
+) -> (StatusCode, axum::Json<serde_json::Value>) {
+    match service
+        .settle(&request.payment_payload, &request.payment_requirements)
+        .await
+    {
+        Ok(response) => (StatusCode::OK, axum::Json(json!(response))),
+        Err(e) => (
+            StatusCode::from_u16(error_status(&e)).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            axum::Json(json!({ "error": format!("Settlement failed: {}", e) })),
+        ),
+    }
+}
@@ -0,0 +1,513 @@
+use crate::error::X402Error;
+use crate::solana::{TransactionBuilder, COMPUTE_BUDGET_PROGRAM_ID, MEMO_PROGRAM_ID};
+use crate::types::{Amount, Network, PaymentPayload, PaymentRequirements, PaymentScheme, SOL_DECIMALS};
+use solana_sdk::{
+    instruction::InstructionError,
+    message::compiled_instruction::CompiledInstruction,
+    pubkey::Pubkey,
+    sanitize::Sanitize,
+    signature::Signature,
+    transaction::{Transaction as SolanaTransaction, TransactionError},
+};
+use solana_system_interface::program as system_program;
+use spl_token::instruction::TokenInstruction;
+use std::fmt;
+
+/// Highest priority fee a sponsored payment may set, in micro-lamports per compute unit
+pub const MAX_COMPUTE_UNIT_PRICE: u64 = 5_000_000;
+
+/// Compute Budget `SetComputeUnitPrice` discriminant
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+/// System program `Transfer` discriminant (bincode, little-endian `u32`)
+const SYSTEM_TRANSFER: [u8; 4] = [2, 0, 0, 0];
+
+/// Why a payment transaction does not satisfy its requirements
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidPaymentReason {
+    SchemeMismatch { expected: PaymentScheme, got: PaymentScheme },
+    NetworkMismatch { expected: Network, got: Network },
+    InvalidRequirements(String),
+    InvalidTransaction(String),
+    InvalidSignature,
+    MissingTransfer,
+    MultipleTransfers,
+    UnexpectedInstruction { program: String },
+    PayerMismatch { expected: String, got: String },
+    RecipientMismatch { expected: String, got: String },
+    AssetMismatch { expected: Option<String>, got: Option<String> },
+    DecimalsMismatch { expected: u8, got: u8 },
+    AmountMismatch { expected: u64, got: u64 },
+    NonceMismatch { expected: String, got: Option<String> },
+    /// The facilitator named as fee payer is not this facilitator
+    FeePayerMismatch { expected: Option<String>, got: String },
+    /// The sponsoring fee payer is referenced by an instruction
+    FeePayerInInstruction { program: String },
+    /// A sponsored payment sets a priority fee above [`MAX_COMPUTE_UNIT_PRICE`]
+    ComputeUnitPriceTooHigh { max: u64, got: u64 },
+    /// Simulation shows the payer cannot cover the payment or the network fee
+    InsufficientFunds { logs: Vec<String> },
+    /// Simulation shows the payer's token account does not exist or is not initialized
+    MissingTokenAccount { logs: Vec<String> },
+    /// Simulation failed for any other reason
+    SimulationFailed { error: String, logs: Vec<String> },
+}
+
+impl InvalidPaymentReason {
+    /// Stable machine-readable code, returned as `invalidReason` by `/verify`
+    pub fn code(&self) -> &'static str {
+        match self {
+            InvalidPaymentReason::SchemeMismatch { .. } => "invalid_scheme",
+            InvalidPaymentReason::NetworkMismatch { .. } => "invalid_network",
+            InvalidPaymentReason::InvalidRequirements(_) => "invalid_payment_requirements",
+            InvalidPaymentReason::InvalidTransaction(_) => "invalid_transaction",
+            InvalidPaymentReason::InvalidSignature => "invalid_transaction_signature",
+            InvalidPaymentReason::MissingTransfer => "missing_transfer_instruction",
+            InvalidPaymentReason::MultipleTransfers => "multiple_transfer_instructions",
+            InvalidPaymentReason::UnexpectedInstruction { .. } => "unexpected_instruction",
+            InvalidPaymentReason::PayerMismatch { .. } => "payer_mismatch",
+            InvalidPaymentReason::RecipientMismatch { .. } => "recipient_mismatch",
+            InvalidPaymentReason::AssetMismatch { .. } => "asset_mismatch",
+            InvalidPaymentReason::DecimalsMismatch { .. } => "decimals_mismatch",
+            InvalidPaymentReason::AmountMismatch { .. } => "amount_mismatch",
+            InvalidPaymentReason::NonceMismatch { .. } => "nonce_mismatch",
+            InvalidPaymentReason::FeePayerMismatch { .. } => "fee_payer_mismatch",
+            InvalidPaymentReason::FeePayerInInstruction { .. } => "fee_payer_in_instruction",
+            InvalidPaymentReason::ComputeUnitPriceTooHigh { .. } => "compute_unit_price_too_high",
+            InvalidPaymentReason::InsufficientFunds { .. } => "insufficient_funds",
+            InvalidPaymentReason::MissingTokenAccount { .. } => "missing_token_account",
+            InvalidPaymentReason::SimulationFailed { .. } => "simulation_failed",
+        }
+    }
+}
+
+impl fmt::Display for InvalidPaymentReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sol = |asset: &Option<String>| asset.clone().unwrap_or_else(|| "SOL".to_string());
+        match self {
+            InvalidPaymentReason::SchemeMismatch { expected, got } => {
+                write!(f, "Expected scheme {:?}, got {:?}", expected, got)
+            }
+            InvalidPaymentReason::NetworkMismatch { expected, got } => {
+                write!(f, "Expected network {:?}, got {:?}", expected, got)
+            }
+            InvalidPaymentReason::InvalidRequirements(msg) => {
+                write!(f, "Invalid payment requirements: {}", msg)
+            }
+            InvalidPaymentReason::InvalidTransaction(msg) => write!(f, "Invalid transaction: {}", msg),
+            InvalidPaymentReason::InvalidSignature => {
+                write!(f, "Transaction signatures are missing or invalid")
+            }
+            InvalidPaymentReason::MissingTransfer => {
+                write!(f, "Transaction has no SOL transfer or SPL transfer_checked instruction")
+            }
+            InvalidPaymentReason::MultipleTransfers => {
+                write!(f, "Transaction has more than one transfer instruction")
+            }
+            InvalidPaymentReason::UnexpectedInstruction { program } => {
+                write!(f, "Unexpected instruction for program {}", program)
+            }
+            InvalidPaymentReason::PayerMismatch { expected, got } => {
+                write!(f, "Payment claims payer {}, but the transfer is from {}", expected, got)
+            }
+            InvalidPaymentReason::RecipientMismatch { expected, got } => {
+                write!(f, "Expected payment to {}, got {}", expected, got)
+            }
+            InvalidPaymentReason::AssetMismatch { expected, got } => {
+                write!(f, "Expected asset {}, got {}", sol(expected), sol(got))
+            }
+            InvalidPaymentReason::DecimalsMismatch { expected, got } => {
+                write!(f, "Expected {} decimals, got {}", expected, got)
+            }
+            InvalidPaymentReason::AmountMismatch { expected, got } => {
+                write!(f, "Expected amount {}, got {}", expected, got)
+            }
+            InvalidPaymentReason::NonceMismatch { expected, got } => match got {
+                Some(got) => write!(f, "Expected nonce {}, got {}", expected, got),
+                None => write!(f, "Expected nonce {}, but the transaction has no memo", expected),
+            },
+            InvalidPaymentReason::FeePayerMismatch { expected, got } => match expected {
+                Some(expected) => write!(f, "Expected fee payer {}, got {}", expected, got),
+                None => write!(f, "Fee payer {} is not offered by this facilitator", got),
+            },
+            InvalidPaymentReason::FeePayerInInstruction { program } => {
+                write!(f, "Fee payer must not be used by the {} instruction", program)
+            }
+            InvalidPaymentReason::ComputeUnitPriceTooHigh { max, got } => write!(
+                f,
+                "Compute unit price {} exceeds the maximum of {} micro-lamports",
+                got, max
+            ),
+            InvalidPaymentReason::InsufficientFunds { logs } => {
+                write!(f, "Payer has insufficient funds")?;
+                write_last_log(f, logs)
+            }
+            InvalidPaymentReason::MissingTokenAccount { logs } => {
+                write!(f, "Payer token account does not exist")?;
+                write_last_log(f, logs)
+            }
+            InvalidPaymentReason::SimulationFailed { error, logs } => {
+                write!(f, "Transaction simulation failed: {}", error)?;
+                write_last_log(f, logs)
+            }
+        }
+    }
+}
+
+/// Append the last program log line, which usually names the failing check
+fn write_last_log(f: &mut fmt::Formatter<'_>, logs: &[String]) -> fmt::Result {
+    match logs.last() {
+        Some(log) => write!(f, " ({})", log),
+        None => Ok(()),
+    }
+}
+
+impl From<InvalidPaymentReason> for X402Error {
+    fn from(reason: InvalidPaymentReason) -> Self {
+        X402Error::PaymentVerificationFailed(reason.to_string())
+    }
+}
+
+/// Transfer found in a payment transaction that satisfies its requirements
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedTransfer {
+    /// Transfer authority: the SOL sender or the token account owner
+    pub payer: Pubkey,
+    /// Account credited: `payTo` for SOL, its token account for SPL tokens
+    pub destination: Pubkey,
+    /// Token mint, or `None` for native SOL
+    pub mint: Option<Pubkey>,
+    /// Amount transferred in atomic units
+    pub amount: u64,
+}
+
+/// A transfer instruction decoded from the transaction
+struct Transfer {
+    authority: Pubkey,
+    destination: Pubkey,
+    mint: Option<Pubkey>,
+    amount: u64,
+    decimals: Option<u8>,
+}
+
+/// Check that a signed payment transaction pays exactly what `requirements` ask for.
+///
+/// The transaction must carry valid signatures and exactly one System `transfer`
+/// or SPL `transfer_checked` instruction to `payTo` (or its associated token
+/// account) for the required asset, decimals and amount. Besides the transfer
+/// only a memo, Compute Budget instructions and creation of the recipient's
+/// associated token account are allowed. When the requirements carry a nonce
+/// the memo must match it.
+///
+/// When `extra.feePayer` names the transaction's fee payer, its signature may be
+/// missing (the facilitator adds it at settle time), it must not appear in any
+/// instruction and the priority fee is capped at [`MAX_COMPUTE_UNIT_PRICE`].
+pub fn verify_payment(
+    payload: &PaymentPayload,
+    requirements: &PaymentRequirements,
+) -> Result<VerifiedTransfer, InvalidPaymentReason> {
+    if payload.scheme != requirements.scheme {
+        return Err(InvalidPaymentReason::SchemeMismatch {
+            expected: requirements.scheme.clone(),
+            got: payload.scheme.clone(),
+        });
+    }
+    if payload.network != requirements.network {
+        return Err(InvalidPaymentReason::NetworkMismatch {
+            expected: requirements.network.clone(),
+            got: payload.network.clone(),
+        });
+    }
+
+    let pay_to = parse_pubkey("payTo", &requirements.pay_to)?;
+    let mint = requirements
+        .asset
+        .as_deref()
+        .map(|asset| parse_pubkey("asset", asset))
+        .transpose()?;
+
+    let transaction = TransactionBuilder::deserialize_transaction(&payload.signed_transaction)
+        .map_err(|e| InvalidPaymentReason::InvalidTransaction(e.to_string()))?;
+    transaction
+        .sanitize()
+        .map_err(|e| InvalidPaymentReason::InvalidTransaction(e.to_string()))?;
+
+    // In fee-payer mode the facilitator's signature is added at settle time
+    let sponsor = requirements
+        .fee_payer()
+        .map(|fee_payer| parse_pubkey("feePayer", fee_payer))
+        .transpose()?;
+    let sponsored = sponsor.is_some() && transaction.message.account_keys.first() == sponsor.as_ref();
+    let signatures_valid = transaction
+        .verify_with_results()
+        .into_iter()
+        .zip(&transaction.signatures)
+        .enumerate()
+        .all(|(index, (valid, signature))| {
+            valid || (sponsored && index == 0 && *signature == Signature::default())
+        });
+    if transaction.signatures.is_empty() || !signatures_valid {
+        return Err(InvalidPaymentReason::InvalidSignature);
+    }
+
+    let recipient_ata =
+        mint.map(|mint| spl_associated_token_account::get_associated_token_address(&pay_to, &mint));
+
+    let mut transfer = None;
+    let mut memo = None;
+    for ix in &transaction.message.instructions {
+        let program = account_key(&transaction, ix.program_id_index)?;
+        // The fee payer only pays fees; it must never move funds or sign for anything
+        if sponsored && ix.accounts.contains(&0) {
+            return Err(InvalidPaymentReason::FeePayerInInstruction {
+                program: program.to_string(),
+            });
+        }
+        if program == MEMO_PROGRAM_ID && memo.is_none() {
+            memo = Some(String::from_utf8_lossy(&ix.data).into_owned());
+            continue;
+        }
+        if program == COMPUTE_BUDGET_PROGRAM_ID {
+            if let (true, Some(price)) = (sponsored, compute_unit_price(ix)) {
+                if price > MAX_COMPUTE_UNIT_PRICE {
+                    return Err(InvalidPaymentReason::ComputeUnitPriceTooHigh {
+                        max: MAX_COMPUTE_UNIT_PRICE,
+                        got: price,
+                    });
+                }
+            }
+            continue;
+        }
+        if program == spl_associated_token_account::ID
+            && creates_account(&transaction, ix, recipient_ata, mint)?
+        {
+            continue;
+        }
+
+        let decoded = if program == system_program::ID {
+            decode_system_transfer(&transaction, ix)?
+        } else if program == spl_token::ID {
+            decode_transfer_checked(&transaction, ix)?
+        } else {
+            None
+        };
+        let Some(decoded) = decoded else {
+            return Err(InvalidPaymentReason::UnexpectedInstruction {
+                program: program.to_string(),
+            });
+        };
+        if transfer.replace(decoded).is_some() {
+            return Err(InvalidPaymentReason::MultipleTransfers);
+        }
+    }
+    let transfer = transfer.ok_or(InvalidPaymentReason::MissingTransfer)?;
+
+    if transfer.mint != mint {
+        return Err(InvalidPaymentReason::AssetMismatch {
+            expected: mint.map(|m| m.to_string()),
+            got: transfer.mint.map(|m| m.to_string()),
+        });
+    }
+
+    let expected_destination = recipient_ata.unwrap_or(pay_to);
+    // A token payment may also name `payTo` directly when it is itself a token account
+    if transfer.destination != expected_destination && transfer.destination != pay_to {
+        return Err(InvalidPaymentReason::RecipientMismatch {
+            expected: expected_destination.to_string(),
+            got: transfer.destination.to_string(),
+        });
+    }
+
+    let decimals = match (requirements.token_decimals(), transfer.decimals) {
+        (Some(expected), Some(got)) if expected != got => {
+            return Err(InvalidPaymentReason::DecimalsMismatch { expected, got });
+        }
+        (Some(decimals), _) | (None, Some(decimals)) => decimals,
+        (None, None) => SOL_DECIMALS,
+    };
+    let expected_amount = Amount::parse(&requirements.max_amount_required, decimals)
+        .map_err(|e| InvalidPaymentReason::InvalidRequirements(e.to_string()))?
+        .atomic();
+    if transfer.amount != expected_amount {
+        return Err(InvalidPaymentReason::AmountMismatch {
+            expected: expected_amount,
+            got: transfer.amount,
+        });
+    }
+
+    if let Some(nonce) = &requirements.nonce {
+        if memo.as_ref() != Some(nonce) {
+            return Err(InvalidPaymentReason::NonceMismatch {
+                expected: nonce.clone(),
+                got: memo,
+            });
+        }
+    }
+
+    if let Ok(from) = payload.from.parse::<Pubkey>() {
+        if from != transfer.authority {
+            return Err(InvalidPaymentReason::PayerMismatch {
+                expected: from.to_string(),
+                got: transfer.authority.to_string(),
+            });
+        }
+    }
+
+    Ok(VerifiedTransfer {
+        payer: transfer.authority,
+        destination: transfer.destination,
+        mint: transfer.mint,
+        amount: transfer.amount,
+    })
+}
+
+fn parse_pubkey(field: &str, value: &str) -> Result<Pubkey, InvalidPaymentReason> {
+    value.parse().map_err(|e| {
+        InvalidPaymentReason::InvalidRequirements(format!("Invalid {} address {}: {}", field, value, e))
+    })
+}
+
+/// Resolve an account index of the transaction message
+fn account_key(transaction: &SolanaTransaction, index: u8) -> Result<Pubkey, InvalidPaymentReason> {
+    transaction
+        .message
+        .account_keys
+        .get(index as usize)
+        .copied()
+        .ok_or_else(|| {
+            InvalidPaymentReason::InvalidTransaction(format!("Account index {} out of range", index))
+        })
+}
+
+/// Resolve the `position`-th account of an instruction
+fn instruction_account(
+    transaction: &SolanaTransaction,
+    ix: &CompiledInstruction,
+    position: usize,
+) -> Result<Pubkey, InvalidPaymentReason> {
+    let index = ix.accounts.get(position).ok_or_else(|| {
+        InvalidPaymentReason::InvalidTransaction("Instruction is missing accounts".to_string())
+    })?;
+    account_key(transaction, *index)
+}
+
+/// Whether an Associated Token Account instruction creates the recipient's token account
+fn creates_account(
+    transaction: &SolanaTransaction,
+    ix: &CompiledInstruction,
+    recipient_ata: Option<Pubkey>,
+    mint: Option<Pubkey>,
+) -> Result<bool, InvalidPaymentReason> {
+    // Create (empty data or 0) and CreateIdempotent (1):
+    // [funding, associated account, wallet, mint, system program, token program]
+    let (Some(recipient_ata), Some(mint)) = (recipient_ata, mint) else {
+        return Ok(false);
+    };
+    if !matches!(ix.data.as_slice(), [] | [0] | [1]) {
+        return Ok(false);
+    }
+    Ok(instruction_account(transaction, ix, 1)? == recipient_ata
+        && instruction_account(transaction, ix, 3)? == mint)
+}
+
+/// Micro-lamports per compute unit set by a Compute Budget `SetComputeUnitPrice`
+fn compute_unit_price(ix: &CompiledInstruction) -> Option<u64> {
+    match ix.data.split_first() {
+        Some((&SET_COMPUTE_UNIT_PRICE, price)) => {
+            <[u8; 8]>::try_from(price).ok().map(u64::from_le_bytes)
+        }
+        _ => None,
+    }
+}
+
+/// Decode a System program `transfer`: [from, to]
+fn decode_system_transfer(
+    transaction: &SolanaTransaction,
+    ix: &CompiledInstruction,
+) -> Result<Option<Transfer>, InvalidPaymentReason> {
+    let Some(lamports) = ix
+        .data
+        .strip_prefix(&SYSTEM_TRANSFER)
+        .and_then(|rest| <[u8; 8]>::try_from(rest).ok())
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(Transfer {
+        authority: instruction_account(transaction, ix, 0)?,
+        destination: instruction_account(transaction, ix, 1)?,
+        mint: None,
+        amount: u64::from_le_bytes(lamports),
+        decimals: None,
+    }))
+}
+
+/// Decode an SPL Token `transfer_checked`: [source, mint, destination, authority]
+fn decode_transfer_checked(
+    transaction: &SolanaTransaction,
+    ix: &CompiledInstruction,
+) -> Result<Option<Transfer>, InvalidPaymentReason> {
+    let Ok(TokenInstruction::TransferChecked { amount, decimals }) =
+        TokenInstruction::unpack(&ix.data)
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(Transfer {
+        authority: instruction_account(transaction, ix, 3)?,
+        destination: instruction_account(transaction, ix, 2)?,
+        mint: Some(instruction_account(transaction, ix, 1)?),
+        amount,
+        decimals: Some(decimals),
+    }))
+}
+
+/// Classify the error of a failed `simulateTransaction` call.
+///
+/// Insufficient lamports or tokens and missing token accounts get their own
+/// reasons; anything else is reported as [`InvalidPaymentReason::SimulationFailed`].
+pub fn simulation_failure(
+    transaction: &SolanaTransaction,
+    error: TransactionError,
+    logs: Vec<String>,
+) -> InvalidPaymentReason {
+    match &error {
+        TransactionError::AccountNotFound
+        | TransactionError::InsufficientFundsForFee
+        | TransactionError::InsufficientFundsForRent { .. } => {
+            return InvalidPaymentReason::InsufficientFunds { logs };
+        }
+        TransactionError::InstructionError(index, ix_error) => {
+            let program = transaction
+                .message
+                .instructions
+                .get(*index as usize)
+                .and_then(|ix| account_key(transaction, ix.program_id_index).ok());
+            match (program, ix_error) {
+                // SystemError::ResultWithNegativeLamports / TokenError::InsufficientFunds
+                (Some(program), InstructionError::Custom(1))
+                    if program == system_program::ID || program == spl_token::ID =>
+                {
+                    return InvalidPaymentReason::InsufficientFunds { logs };
+                }
+                // Uninitialized or non-token source account; TokenError::UninitializedState
+                (
+                    Some(program),
+                    InstructionError::InvalidAccountData
+                    | InstructionError::UninitializedAccount
+                    | InstructionError::IncorrectProgramId
+                    | InstructionError::Custom(9),
+                ) if program == spl_token::ID => {
+                    return InvalidPaymentReason::MissingTokenAccount { logs };
+                }
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+
+    InvalidPaymentReason::SimulationFailed {
+        error: error.to_string(),
+        logs,
+    }
+}
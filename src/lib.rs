@@ -57,21 +57,22 @@
 //!
 //! ```rust,ignore
 //! use actix_web::{web, App, HttpServer, HttpResponse};
-//! use x402_sdk_solana_rust::server::{check_payment, PaymentMiddlewareConfig};
+//! use x402_sdk_solana_rust::server::{PaymentMiddleware, PaymentMiddlewareConfig};
 //! use std::collections::HashMap;
 //!
 //! #[actix_web::main]
 //! async fn main() -> std::io::Result<()> {
-//!     // Configure payment middleware
-//!     let config = PaymentMiddlewareConfig::new(
+//!     // Configure payment middleware once and share it across workers
+//!     let payment = PaymentMiddleware::new(PaymentMiddlewareConfig::new(
 //!         "your-solana-public-key".to_string(),
-//!         HashMap::new(), // route configurations
+//!         HashMap::new(), // route configurations, e.g. "GET /api/data"
 //!         None,           // facilitator config
 //!         None,           // x402 config
-//!     );
+//!     ).expect("valid route keys"));
 //!     
 //!     HttpServer::new(move || {
 //!         App::new()
+//!             .wrap(payment.clone())
 //!             .route("/api/data", web::get().to(|| async {
 //!                 HttpResponse::Ok().json(serde_json::json!({"data": "value"}))
 //!             }))
@@ -123,9 +124,11 @@ pub mod utils;
 pub use error::X402Error;
 pub use types::{
     Network, PaymentPayload, PaymentRequirements, PaymentScheme, X402Config, 
-    FacilitatorConfig, RouteConfig, SvmConfig, TokenConfig,
+    FacilitatorConfig, RouteConfig, SvmConfig, TokenConfig, Price, PricingFn, PricingRequest,
 };
 pub use client::{Fetcher, create_payment_header};
-pub use server::{check_payment, settle_payment, PaymentMiddlewareConfig};
+pub use server::{check_payment, settle_payment, PaymentMiddleware, PaymentMiddlewareConfig};
+#[cfg(feature = "tower")]
+pub use server::PaymentLayer;
 pub use facilitator::Handler;
 pub use solana::{Wallet, TransactionBuilder, create_signer};
\ No newline at end of file
@@ -0,0 +1,462 @@
+use super::nonce::NonceStore;
+use super::routes::{match_route, RouteMatch, Routes};
+use crate::error::X402Error;
+use crate::facilitator::Handler;
+use crate::solana::TransactionBuilder;
+use crate::types::{
+    FacilitatorConfig, PaymentPayload, PaymentRequiredResponse, PaymentRequirements,
+    PaymentScheme, Price, PricingRequest, RouteConfig, SettleResponse, X402Config,
+    DEFAULT_MAX_TIMEOUT_SECONDS,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Payment middleware configuration
+pub struct PaymentMiddlewareConfig {
+    pub pay_to: String,
+    pub routes: Routes,
+    pub facilitator: Option<FacilitatorConfig>,
+    pub x402_config: Option<X402Config>,
+    /// Issues and consumes per-request nonces; replay protection is off when `None`
+    pub nonce_store: Option<Arc<dyn NonceStore>>,
+    /// Facilitator account advertised as `extra.feePayer`; clients then pay no network fee
+    pub fee_payer: Option<String>,
+}
+
+/// Outcome of checking a request against the payment configuration
+#[derive(Debug)]
+pub enum PaymentDecision {
+    /// The route is not priced; the request passes straight through
+    Free,
+    /// The request must be answered with this response instead of the handler
+    Reject(PaymentRejection),
+    /// The payment was verified; settle it once the handler has succeeded
+    Verified(Box<VerifiedPayment>),
+}
+
+/// Transport-agnostic response for a rejected request
+#[derive(Debug, Clone)]
+pub struct PaymentRejection {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// A payment that passed verification and awaits settlement
+#[derive(Debug, Clone)]
+pub struct VerifiedPayment {
+    pub payload: PaymentPayload,
+    pub requirements: PaymentRequirements,
+    /// `"METHOD /path"` of the paid request, which nonces are bound to
+    pub route_id: String,
+    /// Address of the client that paid, when known, which nonces are counted against
+    pub client: Option<String>,
+}
+
+impl PaymentMiddlewareConfig {
+    /// Build the configuration, parsing every route key up front.
+    ///
+    /// Fails with [`X402Error::InvalidInput`] for a key that is not a valid
+    /// [`RoutePattern`](super::RoutePattern).
+    pub fn new(
+        pay_to: String,
+        routes: HashMap<String, RouteConfig>,
+        facilitator: Option<FacilitatorConfig>,
+        x402_config: Option<X402Config>,
+    ) -> Result<Self, X402Error> {
+        Ok(Self {
+            pay_to,
+            routes: Routes::new(routes)?,
+            facilitator,
+            x402_config,
+            nonce_store: None,
+            fee_payer: None,
+        })
+    }
+
+    /// Require payments to commit to a nonce issued by `store`
+    pub fn with_nonce_store(mut self, store: Arc<dyn NonceStore>) -> Self {
+        self.nonce_store = Some(store);
+        self
+    }
+
+    /// Let the facilitator's `fee_payer` account pay network fees for clients.
+    ///
+    /// Use the `feePayer` the facilitator advertises in `/supported`.
+    pub fn with_fee_payer(mut self, fee_payer: impl Into<String>) -> Self {
+        self.fee_payer = Some(fee_payer.into());
+        self
+    }
+
+    /// Find the most specific priced route for a request, if any.
+    ///
+    /// Route keys support path parameters (`/weather/{city}`), wildcards
+    /// (`/files/*`) and `*` / `ANY` as the method; see [`RoutePattern`](super::RoutePattern).
+    pub fn route_for(&self, method: &str, path: &str) -> Option<RouteMatch<'_>> {
+        match_route(&self.routes, method, path)
+    }
+
+    /// Static price of a route, denominated in the configured default token (or SOL)
+    pub fn static_price(&self, route_config: &RouteConfig) -> Price {
+        let asset = self
+            .x402_config
+            .as_ref()
+            .and_then(|c| c.svm_config.as_ref())
+            .and_then(|s| s.default_token.clone());
+
+        Price {
+            amount: route_config.price.clone(),
+            asset,
+        }
+    }
+
+    /// Price a request for a matched route, calling its pricing function if it has one
+    pub async fn price_for(
+        &self,
+        route: &RouteMatch<'_>,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+    ) -> Result<Price, X402Error> {
+        let Some(pricing) = &route.config.pricing else {
+            return Ok(self.static_price(route.config));
+        };
+
+        let request = PricingRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            params: route.params.clone(),
+            headers: headers.clone(),
+            body_size: headers
+                .get("content-length")
+                .and_then(|len| len.trim().parse().ok()),
+        };
+        pricing.price(request).await
+    }
+
+    /// Build the payment requirements for a priced route.
+    ///
+    /// Fails if the price is not a valid amount for its token.
+    pub fn build_requirements(
+        &self,
+        route_config: &RouteConfig,
+        price: &Price,
+        resource: &str,
+    ) -> Result<PaymentRequirements, X402Error> {
+        Ok(PaymentRequirements {
+            scheme: PaymentScheme::Exact,
+            network: route_config.network.clone(),
+            // Clients are always quoted exact atomic units
+            max_amount_required: price.amount()?.to_atomic_string(),
+            resource: resource.to_string(),
+            description: route_config.description.clone().unwrap_or_default(),
+            mime_type: route_config
+                .mime_type
+                .clone()
+                .unwrap_or_else(|| "application/json".to_string()),
+            pay_to: self.pay_to.clone(),
+            max_timeout_seconds: route_config
+                .max_timeout_seconds
+                .unwrap_or(DEFAULT_MAX_TIMEOUT_SECONDS),
+            asset: price.asset.as_ref().map(|t| t.address.clone()),
+            output_schema: None,
+            extra: self.requirements_extra(price),
+            nonce: None,
+        })
+    }
+
+    /// `extra` for a price: token decimals and name, plus the sponsoring fee payer
+    fn requirements_extra(&self, price: &Price) -> Option<serde_json::Value> {
+        let mut extra = serde_json::Map::new();
+        if let Some(token) = &price.asset {
+            extra.insert("decimals".to_string(), json!(token.decimals));
+            extra.insert("name".to_string(), json!(token.name));
+        }
+        if let Some(fee_payer) = &self.fee_payer {
+            extra.insert("feePayer".to_string(), json!(fee_payer));
+        }
+        (!extra.is_empty()).then_some(serde_json::Value::Object(extra))
+    }
+
+    /// Build every accepted payment option for a matched route.
+    ///
+    /// The first entry is the route's primary price (static or dynamic), followed by
+    /// the additional options from [`RouteConfig::accepts`].
+    pub async fn accepts_for(
+        &self,
+        route: &RouteMatch<'_>,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+    ) -> Result<Vec<PaymentRequirements>, X402Error> {
+        let price = self.price_for(route, method, path, headers).await?;
+        let resource = resource_url(path, headers);
+        std::iter::once(&price)
+            .chain(&route.config.accepts)
+            .map(|price| self.build_requirements(route.config, price, &resource))
+            .collect()
+    }
+
+    /// Price the request, verify its payment header and decide how to answer.
+    ///
+    /// `headers` holds the request headers with lowercase names; the payment is
+    /// read from `x-payment`. `client` is the requester's address, if known, and
+    /// limits how many nonces it can hold (see [`NonceStore::issue`]).
+    pub async fn authorize(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+        client: Option<&str>,
+    ) -> Result<PaymentDecision, X402Error> {
+        // Check if route requires payment
+        let Some(route) = self.route_for(method, path) else {
+            return Ok(PaymentDecision::Free);
+        };
+        let accepts = self.accepts_for(&route, method, path, headers).await?;
+        let route_id = format!("{} {}", method, path);
+        let ttl = Duration::from_secs(
+            route
+                .config
+                .max_timeout_seconds
+                .unwrap_or(DEFAULT_MAX_TIMEOUT_SECONDS),
+        );
+
+        let Some(payment_header) = headers.get("x-payment") else {
+            // No payment provided, return 402 with payment requirements
+            return self
+                .payment_required("X-PAYMENT header is required", accepts, &route_id, client, ttl)
+                .await;
+        };
+
+        let payload = match PaymentPayload::from_header(payment_header) {
+            Ok(payload) => payload,
+            Err(e) => {
+                return Ok(PaymentDecision::Reject(PaymentRejection {
+                    status: 400,
+                    headers: Vec::new(),
+                    body: e.to_string(),
+                }))
+            }
+        };
+
+        let Some(mut requirements) = matching_requirements(&payload, &accepts) else {
+            let error = "Payment does not match any accepted option";
+            return self.payment_required(error, accepts, &route_id, client, ttl).await;
+        };
+
+        // Replay protection: the transaction must commit to a nonce we issued
+        if let Some(store) = &self.nonce_store {
+            let Some(nonce) = committed_nonce(&payload) else {
+                let error = "Payment must commit to the server-issued nonce";
+                return self.payment_required(error, accepts, &route_id, client, ttl).await;
+            };
+            if let Err(e) = store.check(&nonce, &route_id).await {
+                return self
+                    .payment_required(&e.to_string(), accepts, &route_id, client, ttl)
+                    .await;
+            }
+            requirements.nonce = Some(nonce);
+        }
+
+        // Payment provided, verify it with the facilitator
+        let handler = Handler::new(self.facilitator.clone());
+        match handler.verify(&payload, &requirements).await {
+            // The nonce is used up by settlement, so a handler error leaves the
+            // client free to send the same payment again
+            Ok(verify_response) if verify_response.verified => {
+                Ok(PaymentDecision::Verified(Box::new(VerifiedPayment {
+                    payload,
+                    requirements,
+                    route_id,
+                    client: client.map(str::to_string),
+                })))
+            }
+            // Tell the client why the facilitator refused the payment
+            Ok(verify_response) => {
+                let error = verify_response
+                    .invalid_reason
+                    .or(verify_response.message)
+                    .unwrap_or_else(|| "Payment verification failed".to_string());
+                self.payment_required(&error, accepts, &route_id, client, ttl).await
+            }
+            // Not the client's fault; its payment and nonce stay usable
+            Err(e) => Ok(PaymentDecision::Reject(facilitator_unavailable(&e))),
+        }
+    }
+
+    /// Build a 402 rejection, attaching a fresh nonce when replay protection is on
+    async fn payment_required(
+        &self,
+        error: &str,
+        accepts: Vec<PaymentRequirements>,
+        route_id: &str,
+        client: Option<&str>,
+        ttl: Duration,
+    ) -> Result<PaymentDecision, X402Error> {
+        self.payment_rejection(error, accepts, route_id, client, ttl)
+            .await
+            .map(PaymentDecision::Reject)
+    }
+
+    /// The 402 response built by [`payment_required`](Self::payment_required)
+    async fn payment_rejection(
+        &self,
+        error: &str,
+        mut accepts: Vec<PaymentRequirements>,
+        route_id: &str,
+        client: Option<&str>,
+        ttl: Duration,
+    ) -> Result<PaymentRejection, X402Error> {
+        if let Some(store) = &self.nonce_store {
+            let nonce = store.issue(route_id, client, ttl).await?;
+            for requirements in &mut accepts {
+                requirements.nonce = Some(nonce.clone());
+            }
+        }
+        payment_required_response(error, accepts)
+    }
+
+    /// Settle a verified payment with the facilitator, then use up its nonce.
+    ///
+    /// If the payment was not settled, returns the response to send instead of
+    /// the handler's: 402 when the facilitator refused it, 502 when the
+    /// facilitator could not be reached.
+    pub async fn settle(&self, payment: &VerifiedPayment) -> Result<SettleResponse, PaymentRejection> {
+        let handler = Handler::new(self.facilitator.clone());
+        let receipt = match handler.settle(&payment.payload, &payment.requirements).await {
+            Ok(receipt) => receipt,
+            Err(e) => return Err(facilitator_unavailable(&e)),
+        };
+        if receipt.settled {
+            if let (Some(store), Some(nonce)) = (&self.nonce_store, &payment.requirements.nonce) {
+                // The chain settles a transaction only once, so a nonce already
+                // used by a concurrent request cannot have been paid twice
+                let _ = store.consume(nonce, &payment.route_id).await;
+            }
+            return Ok(receipt);
+        }
+
+        let error = receipt
+            .error_reason
+            .or(receipt.message)
+            .unwrap_or_else(|| "Payment settlement failed".to_string());
+        let mut requirements = payment.requirements.clone();
+        requirements.nonce = None;
+        let ttl = Duration::from_secs(requirements.max_timeout_seconds);
+        Err(self
+            .payment_rejection(
+                &error,
+                vec![requirements],
+                &payment.route_id,
+                payment.client.as_deref(),
+                ttl,
+            )
+            .await
+            .unwrap_or_else(|e| internal_error(&e)))
+    }
+}
+
+/// Absolute URL of the requested resource, falling back to the bare path without a host
+fn resource_url(path: &str, headers: &HashMap<String, String>) -> String {
+    match headers.get("host") {
+        Some(host) => {
+            let scheme = headers
+                .get("x-forwarded-proto")
+                .map(String::as_str)
+                .unwrap_or("http");
+            format!("{}://{}{}", scheme, host, path)
+        }
+        None => path.to_string(),
+    }
+}
+
+/// JSON error response with the given status
+fn error_response(status: u16, error: String) -> PaymentRejection {
+    PaymentRejection {
+        status,
+        headers: vec![("content-type".to_string(), "application/json".to_string())],
+        body: json!({ "error": error }).to_string(),
+    }
+}
+
+/// 502 for a facilitator that could not be reached or answered with an error
+fn facilitator_unavailable(error: &X402Error) -> PaymentRejection {
+    error_response(502, format!("Facilitator unavailable: {}", error))
+}
+
+/// 500 for a failure on this server's side
+fn internal_error(error: &X402Error) -> PaymentRejection {
+    error_response(500, error.to_string())
+}
+
+/// Build a 402 rejection carrying the spec body and the legacy `x-payment-required` header
+fn payment_required_response(
+    error: &str,
+    accepts: Vec<PaymentRequirements>,
+) -> Result<PaymentRejection, X402Error> {
+    let mut headers = vec![("content-type".to_string(), "application/json".to_string())];
+    // Older clients only read the first option from the header
+    if let Some(first) = accepts.first() {
+        headers.push(("x-payment-required".to_string(), serde_json::to_string(first)?));
+    }
+
+    let body = serde_json::to_string(&PaymentRequiredResponse {
+        x402_version: 1,
+        error: error.to_string(),
+        accepts,
+    })?;
+
+    Ok(PaymentRejection {
+        status: 402,
+        headers,
+        body,
+    })
+}
+
+/// Nonce a payment commits to through its memo instruction, if any
+pub(super) fn committed_nonce(payload: &PaymentPayload) -> Option<String> {
+    TransactionBuilder::deserialize_transaction(&payload.signed_transaction)
+        .ok()
+        .and_then(|tx| TransactionBuilder::find_memo(&tx))
+}
+
+/// Pick the accepted option a payment was made for.
+///
+/// SPL token payments are matched by the mint of their `transfer_checked`
+/// instruction; payments without one are matched to the native SOL option.
+pub(super) fn matching_requirements(
+    payload: &PaymentPayload,
+    accepts: &[PaymentRequirements],
+) -> Option<PaymentRequirements> {
+    let candidates: Vec<&PaymentRequirements> = accepts
+        .iter()
+        .filter(|r| r.scheme == payload.scheme && r.network == payload.network)
+        .collect();
+    if let [only] = candidates.as_slice() {
+        return Some((*only).clone());
+    }
+
+    let mint = TransactionBuilder::deserialize_transaction(&payload.signed_transaction)
+        .ok()
+        .and_then(|tx| {
+            let keys = &tx.message.account_keys;
+            tx.message.instructions.iter().find_map(|ix| {
+                let program = keys.get(ix.program_id_index as usize)?;
+                // transfer_checked: [source, mint, destination, authority]
+                let is_transfer_checked =
+                    *program == spl_token::ID && ix.data.first() == Some(&12);
+                is_transfer_checked
+                    .then(|| keys.get(*ix.accounts.get(1)? as usize))
+                    .flatten()
+                    .map(|mint| mint.to_string())
+            })
+        });
+
+    candidates
+        .into_iter()
+        .find(|r| r.asset == mint)
+        .cloned()
+}
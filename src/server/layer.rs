@@ -0,0 +1,174 @@
+use super::core::{PaymentDecision, PaymentMiddlewareConfig, PaymentRejection};
+use futures_util::future::BoxFuture;
+use http::{HeaderName, HeaderValue, Request, Response, StatusCode};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Tower layer that gates priced routes behind an x402 payment.
+///
+/// This is the axum/hyper counterpart of
+/// [`PaymentMiddleware`](super::PaymentMiddleware) and shares the same
+/// [`PaymentMiddlewareConfig`] and semantics: unpriced routes pass through,
+/// missing or invalid payments are rejected, and verified payments are settled
+/// only after the inner service responds with a 2xx status.
+///
+/// ```rust,ignore
+/// let app = axum::Router::new()
+///     .route("/weather", get(weather))
+///     .layer(PaymentLayer::new(config));
+/// ```
+#[derive(Clone)]
+pub struct PaymentLayer {
+    config: Arc<PaymentMiddlewareConfig>,
+}
+
+impl PaymentLayer {
+    /// Create a new payment layer from its configuration
+    pub fn new(config: PaymentMiddlewareConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+        }
+    }
+
+    /// Create a payment layer sharing an existing configuration
+    pub fn from_shared(config: Arc<PaymentMiddlewareConfig>) -> Self {
+        Self { config }
+    }
+}
+
+impl<S> Layer<S> for PaymentLayer {
+    type Service = PaymentService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PaymentService {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Service produced by [`PaymentLayer`]
+#[derive(Clone)]
+pub struct PaymentService<S> {
+    inner: S,
+    config: Arc<PaymentMiddlewareConfig>,
+}
+
+/// IP address of the connected client, which its nonces are counted against.
+///
+/// Read from a `SocketAddr` request extension, or axum's `ConnectInfo` when the
+/// router is served with `into_make_service_with_connect_info::<SocketAddr>()`.
+fn client_address<B>(req: &Request<B>) -> Option<String> {
+    let extensions = req.extensions();
+    #[cfg(feature = "axum")]
+    if let Some(axum::extract::ConnectInfo(addr)) =
+        extensions.get::<axum::extract::ConnectInfo<SocketAddr>>()
+    {
+        return Some(addr.ip().to_string());
+    }
+    extensions.get::<SocketAddr>().map(|addr| addr.ip().to_string())
+}
+
+/// Convert a transport-agnostic rejection into an `http` response
+fn rejection_response<B: From<String>>(rejection: PaymentRejection) -> Response<B> {
+    let mut response = Response::new(B::from(rejection.body));
+    *response.status_mut() =
+        StatusCode::from_u16(rejection.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    for (name, value) in rejection.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    response
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for PaymentService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+    ResBody: From<String> + Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        // Take the service that was driven to readiness and leave a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let config = self.config.clone();
+
+        Box::pin(async move {
+            // Unpriced routes pass through without looking at the headers
+            if config.route_for(req.method().as_str(), req.uri().path()).is_none() {
+                return inner.call(req).await;
+            }
+
+            // Collect the request headers with lowercase names, skipping values
+            // that are not visible ASCII
+            let headers: HashMap<String, String> = req
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect();
+
+            let client = client_address(&req);
+            let decision = config
+                .authorize(
+                    req.method().as_str(),
+                    req.uri().path(),
+                    &headers,
+                    client.as_deref(),
+                )
+                .await;
+
+            let payment = match decision {
+                // Unpriced routes pass straight through
+                Ok(PaymentDecision::Free) => return inner.call(req).await,
+                // Missing or invalid payment short-circuits with the rejection
+                Ok(PaymentDecision::Reject(rejection)) => {
+                    return Ok(rejection_response(rejection))
+                }
+                Ok(PaymentDecision::Verified(payment)) => payment,
+                Err(e) => {
+                    return Ok(rejection_response(PaymentRejection {
+                        status: 500,
+                        headers: Vec::new(),
+                        body: e.to_string(),
+                    }))
+                }
+            };
+
+            let mut res = inner.call(req).await?;
+
+            // Only charge for requests that were actually served
+            if res.status().is_success() {
+                match config.settle(&payment).await {
+                    Ok(receipt) => {
+                        if let Ok(Ok(value)) = receipt.to_header().map(HeaderValue::try_from) {
+                            res.headers_mut()
+                                .insert(HeaderName::from_static("x-payment-response"), value);
+                        }
+                    }
+                    // Unpaid content is not handed out
+                    Err(rejection) => return Ok(rejection_response(rejection)),
+                }
+            }
+
+            Ok(res)
+        })
+    }
+}
@@ -1,33 +1,65 @@
-use crate::facilitator::Handler;
-use crate::types::{
-    FacilitatorConfig, PaymentPayload, PaymentRequirements, PaymentScheme, RouteConfig,
-    X402Config,
+use super::core::{
+    committed_nonce, matching_requirements, PaymentDecision, PaymentMiddlewareConfig, PaymentRejection,
+    VerifiedPayment,
 };
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::StatusCode;
 use actix_web::{Error, HttpRequest, HttpResponse};
+use crate::types::{PaymentPayload, SettleResponse};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
 use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Collect the request headers with lowercase names, skipping values that are
+/// not visible ASCII
+fn request_headers(req: &HttpRequest) -> HashMap<String, String> {
+    req.headers()
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = value.to_str().ok()?;
+            Some((name.as_str().to_ascii_lowercase(), value.to_string()))
+        })
+        .collect()
+}
 
-/// Payment middleware configuration
-pub struct PaymentMiddlewareConfig {
-    pub pay_to: String,
-    pub routes: HashMap<String, RouteConfig>,
-    pub facilitator: Option<FacilitatorConfig>,
-    pub x402_config: Option<X402Config>,
+/// IP address of the connected client, which its nonces are counted against
+fn client_address(req: &HttpRequest) -> Option<String> {
+    req.peer_addr().map(|addr| addr.ip().to_string())
 }
 
-impl PaymentMiddlewareConfig {
-    pub fn new(
-        pay_to: String,
-        routes: HashMap<String, RouteConfig>,
-        facilitator: Option<FacilitatorConfig>,
-        x402_config: Option<X402Config>,
-    ) -> Self {
-        Self {
-            pay_to,
-            routes,
-            facilitator,
-            x402_config,
-        }
+/// Convert a transport-agnostic rejection into an actix response
+fn rejection_response(rejection: PaymentRejection) -> HttpResponse {
+    let status =
+        StatusCode::from_u16(rejection.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut builder = HttpResponse::build(status);
+    for (name, value) in rejection.headers {
+        builder.insert_header((name, value));
+    }
+    builder.body(rejection.body)
+}
+
+/// Run the payment gate for an actix request
+async fn authorize(
+    req: &HttpRequest,
+    config: &PaymentMiddlewareConfig,
+) -> Result<PaymentDecision, Error> {
+    // Unpriced routes pass through without looking at the headers
+    if config.route_for(req.method().as_str(), req.path()).is_none() {
+        return Ok(PaymentDecision::Free);
     }
+    let client = client_address(req);
+    config
+        .authorize(
+            req.method().as_str(),
+            req.path(),
+            &request_headers(req),
+            client.as_deref(),
+        )
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)
 }
 
 /// Check if payment is required and validate payment for a request
@@ -35,152 +67,166 @@ pub async fn check_payment(
     req: &HttpRequest,
     config: &PaymentMiddlewareConfig,
 ) -> Result<Option<HttpResponse>, Error> {
+    match authorize(req, config).await? {
+        PaymentDecision::Reject(rejection) => Ok(Some(rejection_response(rejection))),
+        PaymentDecision::Free | PaymentDecision::Verified(_) => Ok(None),
+    }
+}
+
+/// Settle payment after successful request.
+///
+/// Returns the facilitator's receipt, which callers should send back in the
+/// `X-PAYMENT-RESPONSE` header (see [`SettleResponse::to_header`]). If the
+/// payment could not be settled, the error carries the 402 or 502 response to
+/// send instead of the handler's.
+pub async fn settle_payment(
+    req: &HttpRequest,
+    config: &PaymentMiddlewareConfig,
+) -> Result<Option<SettleResponse>, Error> {
     let method = req.method().as_str();
-    let path = req.path();
-    let route_key = format!("{} {}", method, path);
-
-    // Check if route requires payment
-    if let Some(route_config) = config.routes.get(&route_key) {
-        // Check for X-PAYMENT header
-        let payment_header = req.headers().get("x-payment");
-
-        if payment_header.is_none() {
-            // No payment provided, return 402 with payment requirements
-            // Extract token information from config if available
-            let (token_address, token_decimals, token_name) = config.x402_config.as_ref()
-                .and_then(|c| c.svm_config.as_ref())
-                .and_then(|s| s.default_token.as_ref())
-                .map(|t| (
-                    Some(t.address.clone()),
-                    Some(t.decimals),
-                    Some(t.name.clone())
-                ))
-                .unwrap_or((None, None, None));
-
-            let requirements = PaymentRequirements {
-                x402_version: 1,
-                scheme: PaymentScheme::Exact,
-                network: route_config.network.clone(),
-                max_amount_required: route_config.price.clone(),
-                pay_to: config.pay_to.clone(),
-                token_address,
-                token_decimals,
-                token_name,
-                memo: route_config.description.clone(),
-                nonce: None,
-            };
+    let Some(route) = config.route_for(method, req.path()) else {
+        return Ok(None);
+    };
+    let headers = request_headers(req);
+    let Some(payment_str) = headers.get("x-payment") else {
+        return Ok(None);
+    };
+
+    let payment_payload =
+        PaymentPayload::from_header(payment_str).map_err(actix_web::error::ErrorBadRequest)?;
+    let accepts = config
+        .accepts_for(&route, method, req.path(), &headers)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let Some(mut requirements) = matching_requirements(&payment_payload, &accepts) else {
+        return Ok(None);
+    };
+    if config.nonce_store.is_some() {
+        requirements.nonce = committed_nonce(&payment_payload);
+    }
+    let payment = VerifiedPayment {
+        payload: payment_payload,
+        requirements,
+        route_id: format!("{} {}", method, req.path()),
+        client: client_address(req),
+    };
+
+    match config.settle(&payment).await {
+        Ok(receipt) => Ok(Some(receipt)),
+        Err(rejection) => Err(actix_web::error::InternalError::from_response(
+            "Payment settlement failed",
+            rejection_response(rejection),
+        )
+        .into()),
+    }
+}
 
-            let requirements_json = serde_json::to_string(&requirements)
-                .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+/// Actix-web middleware that gates priced routes behind an x402 payment.
+///
+/// Requests to routes listed in [`PaymentMiddlewareConfig::routes`] without a
+/// valid `x-payment` header are answered with `402 Payment Required`. Paid
+/// requests are verified before the wrapped handler runs and settled only if
+/// the handler responds with a 2xx status. All other requests pass through.
+///
+/// ```rust,ignore
+/// let config = PaymentMiddlewareConfig::new(pay_to, routes, facilitator, None)?;
+/// let middleware = PaymentMiddleware::new(config);
+///
+/// HttpServer::new(move || App::new().wrap(middleware.clone()).route(...))
+/// ```
+#[derive(Clone)]
+pub struct PaymentMiddleware {
+    config: Arc<PaymentMiddlewareConfig>,
+}
 
-            return Ok(Some(
-                HttpResponse::PaymentRequired()
-                    .insert_header(("x-payment-required", requirements_json))
-                    .finish(),
-            ));
+impl PaymentMiddleware {
+    /// Create a new payment middleware from its configuration
+    pub fn new(config: PaymentMiddlewareConfig) -> Self {
+        Self {
+            config: Arc::new(config),
         }
+    }
 
-        // Payment provided, verify it
-        if let Some(payment_value) = payment_header {
-            let payment_str = payment_value
-                .to_str()
-                .map_err(|e| actix_web::error::ErrorBadRequest(e))?;
-            let payment_payload: PaymentPayload = serde_json::from_str(payment_str)
-                .map_err(|e| actix_web::error::ErrorBadRequest(e))?;
-
-            // Extract token information from config if available
-            let (token_address, token_decimals, token_name) = config.x402_config.as_ref()
-                .and_then(|c| c.svm_config.as_ref())
-                .and_then(|s| s.default_token.as_ref())
-                .map(|t| (
-                    Some(t.address.clone()),
-                    Some(t.decimals),
-                    Some(t.name.clone())
-                ))
-                .unwrap_or((None, None, None));
-
-            // Verify payment with facilitator
-            let handler = Handler::new(config.facilitator.clone());
-            let requirements = PaymentRequirements {
-                x402_version: 1,
-                scheme: PaymentScheme::Exact,
-                network: route_config.network.clone(),
-                max_amount_required: route_config.price.clone(),
-                pay_to: config.pay_to.clone(),
-                token_address,
-                token_decimals,
-                token_name,
-                memo: route_config.description.clone(),
-                nonce: None,
-            };
+    /// Create a payment middleware sharing an existing configuration
+    pub fn from_shared(config: Arc<PaymentMiddlewareConfig>) -> Self {
+        Self { config }
+    }
+}
 
-            match handler.verify(&payment_payload, &requirements).await {
-                Ok(verify_response) => {
-                    if !verify_response.verified {
-                        return Ok(Some(
-                            HttpResponse::PaymentRequired().body("Payment verification failed"),
-                        ));
-                    }
-                    // Payment verified, allow request to proceed
-                }
-                Err(_) => {
-                    return Ok(Some(
-                        HttpResponse::PaymentRequired().body("Payment verification failed"),
-                    ));
-                }
-            }
-        }
+impl<S, B> Transform<S, ServiceRequest> for PaymentMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = PaymentMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(PaymentMiddlewareService {
+            service: Rc::new(service),
+            config: self.config.clone(),
+        }))
     }
+}
 
-    // No payment required or payment verified
-    Ok(None)
+/// Service produced by [`PaymentMiddleware`]
+pub struct PaymentMiddlewareService<S> {
+    service: Rc<S>,
+    config: Arc<PaymentMiddlewareConfig>,
 }
 
-/// Settle payment after successful request
-pub async fn settle_payment(
-    req: &HttpRequest,
-    config: &PaymentMiddlewareConfig,
-) -> Result<(), Error> {
-    let method = req.method().as_str();
-    let path = req.path();
-    let route_key = format!("{} {}", method, path);
-
-    if let Some(route_config) = config.routes.get(&route_key) {
-        if let Some(payment_value) = req.headers().get("x-payment") {
-            let payment_str = payment_value
-                .to_str()
-                .map_err(|e| actix_web::error::ErrorBadRequest(e))?;
-            let payment_payload: PaymentPayload = serde_json::from_str(payment_str)
-                .map_err(|e| actix_web::error::ErrorBadRequest(e))?;
-
-            // Extract token information from config if available
-            let (token_address, token_decimals, token_name) = config.x402_config.as_ref()
-                .and_then(|c| c.svm_config.as_ref())
-                .and_then(|s| s.default_token.as_ref())
-                .map(|t| (
-                    Some(t.address.clone()),
-                    Some(t.decimals),
-                    Some(t.name.clone())
-                ))
-                .unwrap_or((None, None, None));
-
-            let handler = Handler::new(config.facilitator.clone());
-            let requirements = PaymentRequirements {
-                x402_version: 1,
-                scheme: PaymentScheme::Exact,
-                network: route_config.network.clone(),
-                max_amount_required: route_config.price.clone(),
-                pay_to: config.pay_to.clone(),
-                token_address,
-                token_decimals,
-                token_name,
-                memo: route_config.description.clone(),
-                nonce: None,
+impl<S, B> Service<ServiceRequest> for PaymentMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let config = self.config.clone();
+
+        Box::pin(async move {
+            let payment = match authorize(req.request(), &config).await? {
+                // Unpriced routes pass straight through
+                PaymentDecision::Free => {
+                    return service.call(req).await.map(|res| res.map_into_left_body());
+                }
+                // Missing or invalid payment short-circuits with the rejection
+                PaymentDecision::Reject(rejection) => {
+                    let response = rejection_response(rejection);
+                    return Ok(req.into_response(response).map_into_right_body());
+                }
+                PaymentDecision::Verified(payment) => payment,
             };
 
-            let _ = handler.settle(&payment_payload, &requirements).await;
-        }
-    }
+            let mut res = service.call(req).await?;
 
-    Ok(())
-}
\ No newline at end of file
+            // Only charge for requests that were actually served
+            if res.status().is_success() {
+                match config.settle(&payment).await {
+                    Ok(receipt) => {
+                        if let Ok(Ok(value)) = receipt.to_header().map(HeaderValue::try_from) {
+                            res.headers_mut()
+                                .insert(HeaderName::from_static("x-payment-response"), value);
+                        }
+                    }
+                    // Unpaid content is not handed out
+                    Err(rejection) => {
+                        let response = rejection_response(rejection);
+                        return Ok(res.into_response(response).map_into_right_body());
+                    }
+                }
+            }
+
+            Ok(res.map_into_left_body())
+        })
+    }
+}
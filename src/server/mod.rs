@@ -1,3 +1,15 @@
+mod core;
+#[cfg(feature = "tower")]
+mod layer;
 mod middleware;
+mod nonce;
+mod routes;
 
-pub use middleware::{check_payment, settle_payment, PaymentMiddlewareConfig};
\ No newline at end of file
+pub use self::core::{PaymentDecision, PaymentMiddlewareConfig, PaymentRejection, VerifiedPayment};
+#[cfg(feature = "tower")]
+pub use layer::{PaymentLayer, PaymentService};
+pub use middleware::{check_payment, settle_payment, PaymentMiddleware, PaymentMiddlewareService};
+pub use nonce::{
+    InMemoryNonceStore, NonceStore, DEFAULT_MAX_NONCES_PER_CLIENT, DEFAULT_MAX_OUTSTANDING_NONCES,
+};
+pub use routes::{match_route, RouteMatch, RoutePattern, Routes};
@@ -0,0 +1,240 @@
+use crate::error::X402Error;
+use crate::utils::generate_id;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Storage for server-issued payment nonces.
+///
+/// A nonce is issued with each 402 response, bound to the route it was issued
+/// for and an expiry. Paid requests must commit to the nonce in a memo
+/// instruction; the store then consumes it so the same payment cannot be
+/// replayed, nor accepted by a server that did not issue it.
+#[async_trait]
+pub trait NonceStore: Send + Sync {
+    /// Issue a fresh nonce bound to `route`, valid for `ttl`.
+    ///
+    /// `client` identifies the requester (usually its IP address) when known,
+    /// so stores can limit how many nonces one client holds.
+    async fn issue(
+        &self,
+        route: &str,
+        client: Option<&str>,
+        ttl: Duration,
+    ) -> Result<String, X402Error>;
+
+    /// Check a nonce for `route` without consuming it, so bad payments are
+    /// turned away before they are verified. Stores that cannot check cheaply
+    /// may accept every nonce here; [`consume`](Self::consume) has the final say.
+    async fn check(&self, nonce: &str, route: &str) -> Result<(), X402Error> {
+        let _ = (nonce, route);
+        Ok(())
+    }
+
+    /// Consume a nonce for `route`, rejecting unknown, expired, reused or mismatched nonces
+    async fn consume(&self, nonce: &str, route: &str) -> Result<(), X402Error>;
+}
+
+/// Outstanding nonces an [`InMemoryNonceStore`] keeps by default
+pub const DEFAULT_MAX_OUTSTANDING_NONCES: usize = 10_000;
+
+/// Outstanding nonces one client may hold in an [`InMemoryNonceStore`] by default
+pub const DEFAULT_MAX_NONCES_PER_CLIENT: usize = 100;
+
+/// Expiry of a nonce, with an issue counter that keeps equal expiries apart
+type Deadline = (Instant, u64);
+
+struct NonceEntry {
+    route: String,
+    client: Option<String>,
+    deadline: Deadline,
+}
+
+impl NonceEntry {
+    /// Why this entry cannot be used for `route`, if it cannot
+    fn validate(&self, route: &str) -> Result<(), X402Error> {
+        if self.deadline.0 <= Instant::now() {
+            return Err(X402Error::InvalidNonce("expired".to_string()));
+        }
+        if self.route != route {
+            return Err(X402Error::InvalidNonce(format!(
+                "issued for route {}",
+                self.route
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Outstanding nonces, indexed by expiry overall and per client
+#[derive(Default)]
+struct Nonces {
+    entries: HashMap<String, NonceEntry>,
+    by_deadline: BTreeMap<Deadline, String>,
+    by_client: HashMap<String, BTreeMap<Deadline, String>>,
+    issued: u64,
+}
+
+impl Nonces {
+    fn insert(&mut self, nonce: String, route: &str, client: Option<&str>, expires_at: Instant) {
+        self.issued += 1;
+        let deadline = (expires_at, self.issued);
+        self.by_deadline.insert(deadline, nonce.clone());
+        if let Some(client) = client {
+            self.by_client
+                .entry(client.to_string())
+                .or_default()
+                .insert(deadline, nonce.clone());
+        }
+        self.entries.insert(
+            nonce,
+            NonceEntry {
+                route: route.to_string(),
+                client: client.map(str::to_string),
+                deadline,
+            },
+        );
+    }
+
+    fn remove(&mut self, nonce: &str) -> Option<NonceEntry> {
+        let entry = self.entries.remove(nonce)?;
+        self.by_deadline.remove(&entry.deadline);
+        if let Some(client) = &entry.client {
+            if let Some(nonces) = self.by_client.get_mut(client) {
+                nonces.remove(&entry.deadline);
+                if nonces.is_empty() {
+                    self.by_client.remove(client);
+                }
+            }
+        }
+        Some(entry)
+    }
+
+    /// Outstanding nonces issued to `client`
+    fn client_len(&self, client: &str) -> usize {
+        self.by_client.get(client).map_or(0, BTreeMap::len)
+    }
+
+    /// Drop the nonce closest to expiry, among `client`'s if given
+    fn evict_soonest(&mut self, client: Option<&str>) {
+        let index = match client {
+            Some(client) => self.by_client.get(client),
+            None => Some(&self.by_deadline),
+        };
+        if let Some(nonce) = index.and_then(|index| index.values().next()).cloned() {
+            self.remove(&nonce);
+        }
+    }
+
+    fn remove_expired(&mut self, now: Instant) {
+        while let Some((deadline, nonce)) = self.by_deadline.first_key_value() {
+            if deadline.0 > now {
+                break;
+            }
+            let nonce = nonce.clone();
+            self.remove(&nonce);
+        }
+    }
+}
+
+/// In-memory [`NonceStore`] for single-instance servers.
+///
+/// Every 402 issues a nonce, so the store holds at most a fixed number of
+/// outstanding nonces, and at most a fixed number per client; when either is
+/// full, the client's (or the store's) nonce closest to expiry is dropped. A
+/// client flooding the server with requests thus only evicts its own nonces,
+/// as long as the server can tell clients apart.
+pub struct InMemoryNonceStore {
+    nonces: Mutex<Nonces>,
+    capacity: usize,
+    per_client: usize,
+}
+
+impl Default for InMemoryNonceStore {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_MAX_OUTSTANDING_NONCES)
+    }
+}
+
+impl InMemoryNonceStore {
+    /// Create an empty nonce store holding up to [`DEFAULT_MAX_OUTSTANDING_NONCES`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty nonce store holding up to `capacity` outstanding nonces
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            nonces: Mutex::new(Nonces::default()),
+            capacity: capacity.max(1),
+            per_client: DEFAULT_MAX_NONCES_PER_CLIENT,
+        }
+    }
+
+    /// Let each client hold at most `limit` outstanding nonces
+    /// (default [`DEFAULT_MAX_NONCES_PER_CLIENT`])
+    pub fn with_client_limit(mut self, limit: usize) -> Self {
+        self.per_client = limit.max(1);
+        self
+    }
+
+    /// Number of outstanding (issued but not consumed) nonces
+    pub fn len(&self) -> usize {
+        self.nonces.lock().map(|nonces| nonces.entries.len()).unwrap_or(0)
+    }
+
+    /// Whether there are no outstanding nonces
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Nonces>, X402Error> {
+        self.nonces
+            .lock()
+            .map_err(|e| X402Error::InternalError(format!("Nonce store poisoned: {}", e)))
+    }
+}
+
+#[async_trait]
+impl NonceStore for InMemoryNonceStore {
+    async fn issue(
+        &self,
+        route: &str,
+        client: Option<&str>,
+        ttl: Duration,
+    ) -> Result<String, X402Error> {
+        let mut nonces = self.lock()?;
+
+        // Drop expired nonces so the store does not grow without bound
+        let now = Instant::now();
+        nonces.remove_expired(now);
+        if let Some(client) = client {
+            while nonces.client_len(client) >= self.per_client {
+                nonces.evict_soonest(Some(client));
+            }
+        }
+        while nonces.entries.len() >= self.capacity {
+            nonces.evict_soonest(None);
+        }
+
+        let nonce = generate_id();
+        nonces.insert(nonce.clone(), route, client, now + ttl);
+        Ok(nonce)
+    }
+
+    async fn check(&self, nonce: &str, route: &str) -> Result<(), X402Error> {
+        self.lock()?
+            .entries
+            .get(nonce)
+            .ok_or_else(|| X402Error::InvalidNonce("unknown or already used".to_string()))?
+            .validate(route)
+    }
+
+    async fn consume(&self, nonce: &str, route: &str) -> Result<(), X402Error> {
+        self.lock()?
+            .remove(nonce)
+            .ok_or_else(|| X402Error::InvalidNonce("unknown or already used".to_string()))?
+            .validate(route)
+    }
+}
@@ -0,0 +1,228 @@
+use crate::error::X402Error;
+use crate::types::RouteConfig;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// One segment of a route pattern path
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// Matches the segment verbatim, e.g. `weather`
+    Literal(String),
+    /// Glob within a single segment, e.g. `*.csv`
+    Glob(String),
+    /// Named path parameter, e.g. `{city}`
+    Param(String),
+    /// `*` in the middle of a path: exactly one segment
+    Wildcard,
+    /// Trailing `*` or `**`: any remaining segments, including none
+    CatchAll,
+}
+
+impl Segment {
+    /// Rank used for most-specific-wins ordering
+    fn rank(&self) -> u8 {
+        match self {
+            Segment::Literal(_) => 3,
+            Segment::Glob(_) => 2,
+            Segment::Param(_) => 1,
+            Segment::Wildcard | Segment::CatchAll => 0,
+        }
+    }
+}
+
+/// A parsed `RouteConfig` key such as `"GET /weather/{city}"` or `"ANY /api/*"`.
+///
+/// The method may be an HTTP method, `*` or `ANY` (or omitted) to match any
+/// method. Path segments may be literals, `{name}` parameters, `*` for a single
+/// segment, globs like `*.csv` within one segment, or a trailing `*` / `**`
+/// that matches the rest of the path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutePattern {
+    method: Option<String>,
+    segments: Vec<Segment>,
+}
+
+/// A route matched against a request, with its extracted path parameters
+#[derive(Debug, Clone)]
+pub struct RouteMatch<'a> {
+    pub key: &'a str,
+    pub config: &'a RouteConfig,
+    pub params: HashMap<String, String>,
+}
+
+/// Split a path into its non-empty segments
+fn path_segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+/// Match `text` against a glob where `*` stands for any run of characters
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+impl RoutePattern {
+    /// Parse a route key of the form `"[METHOD] /path"`
+    pub fn parse(key: &str) -> Result<Self, X402Error> {
+        let key = key.trim();
+        let (method, path) = match key.split_once(char::is_whitespace) {
+            Some((method, path)) => (Some(method), path.trim()),
+            None => (None, key),
+        };
+
+        if !path.starts_with('/') {
+            return Err(X402Error::InvalidInput(format!(
+                "Route path must start with '/': {}",
+                key
+            )));
+        }
+
+        let method = match method {
+            None | Some("*") => None,
+            Some(m) if m.eq_ignore_ascii_case("ANY") => None,
+            Some(m) => Some(m.to_ascii_uppercase()),
+        };
+
+        let raw: Vec<&str> = path_segments(path).collect();
+        let mut segments = Vec::with_capacity(raw.len());
+        for (index, segment) in raw.iter().enumerate() {
+            let is_last = index + 1 == raw.len();
+            let parsed = if *segment == "**" || (*segment == "*" && is_last) {
+                if !is_last {
+                    return Err(X402Error::InvalidInput(format!(
+                        "'**' is only allowed at the end of a route: {}",
+                        key
+                    )));
+                }
+                Segment::CatchAll
+            } else if *segment == "*" {
+                Segment::Wildcard
+            } else if let Some(name) = segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                if name.is_empty() {
+                    return Err(X402Error::InvalidInput(format!(
+                        "Empty path parameter name in route: {}",
+                        key
+                    )));
+                }
+                Segment::Param(name.to_string())
+            } else if segment.contains('*') {
+                Segment::Glob(segment.to_string())
+            } else {
+                Segment::Literal(segment.to_string())
+            };
+            segments.push(parsed);
+        }
+
+        Ok(Self { method, segments })
+    }
+
+    /// Match a request, returning the extracted path parameters on success
+    pub fn matches(&self, method: &str, path: &str) -> Option<HashMap<String, String>> {
+        if let Some(expected) = &self.method {
+            if !expected.eq_ignore_ascii_case(method) {
+                return None;
+            }
+        }
+
+        let mut params = HashMap::new();
+        let mut actual = path_segments(path);
+        for segment in &self.segments {
+            if *segment == Segment::CatchAll {
+                return Some(params);
+            }
+            let value = actual.next()?;
+            match segment {
+                Segment::Literal(literal) if literal == value => {}
+                Segment::Glob(glob) if glob_matches(glob, value) => {}
+                Segment::Param(name) => {
+                    params.insert(name.clone(), value.to_string());
+                }
+                Segment::Wildcard => {}
+                _ => return None,
+            }
+        }
+
+        actual.next().is_none().then_some(params)
+    }
+
+    /// Order two patterns by specificity; `Greater` means `self` is more specific
+    pub fn specificity_cmp(&self, other: &Self) -> Ordering {
+        let ranks = |p: &Self| {
+            p.segments
+                .iter()
+                .filter(|s| **s != Segment::CatchAll)
+                .map(Segment::rank)
+                .collect::<Vec<_>>()
+        };
+        let exact = |p: &Self| p.segments.last() != Some(&Segment::CatchAll);
+        ranks(self)
+            .cmp(&ranks(other))
+            .then_with(|| exact(self).cmp(&exact(other)))
+            .then_with(|| self.method.is_some().cmp(&other.method.is_some()))
+    }
+}
+
+/// Priced routes keyed by [`RoutePattern`], parsed once when the table is built
+#[derive(Debug, Clone, Default)]
+pub struct Routes {
+    /// Most specific first, so the first match wins
+    routes: Vec<(String, RoutePattern, RouteConfig)>,
+}
+
+impl Routes {
+    /// Parse every route key, failing on the first one that is not a valid pattern
+    pub fn new(routes: HashMap<String, RouteConfig>) -> Result<Self, X402Error> {
+        let mut routes = routes
+            .into_iter()
+            .map(|(key, config)| {
+                let pattern = RoutePattern::parse(&key)?;
+                Ok((key, pattern, config))
+            })
+            .collect::<Result<Vec<_>, X402Error>>()?;
+        // Fall back to the key itself so equal-specificity ties are deterministic
+        routes.sort_by(|(a_key, a, _), (b_key, b, _)| {
+            b.specificity_cmp(a).then_with(|| a_key.cmp(b_key))
+        });
+        Ok(Self { routes })
+    }
+
+    /// Configuration of the route with exactly this key
+    pub fn get(&self, key: &str) -> Option<&RouteConfig> {
+        self.iter().find(|(k, _)| *k == key).map(|(_, config)| config)
+    }
+
+    /// Route keys and configurations, most specific first
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &RouteConfig)> {
+        self.routes.iter().map(|(key, _, config)| (key.as_str(), config))
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+/// Find the most specific route matching a request
+pub fn match_route<'a>(routes: &'a Routes, method: &str, path: &str) -> Option<RouteMatch<'a>> {
+    routes.routes.iter().find_map(|(key, pattern, config)| {
+        let params = pattern.matches(method, path)?;
+        Some(RouteMatch { key, config, params })
+    })
+}
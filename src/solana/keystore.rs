@@ -0,0 +1,161 @@
+use crate::error::X402Error;
+use aes_gcm_siv::aead::{Aead, KeyInit};
+use aes_gcm_siv::{Aes256GcmSiv, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hmac::Hmac;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use solana_sdk::signature::{Keypair, Signer};
+use zeroize::Zeroizing;
+
+/// PBKDF2 rounds used for new keystores
+pub const KEYSTORE_PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// Most PBKDF2 rounds accepted, so a crafted file cannot stall decryption
+pub const MAX_KEYSTORE_PBKDF2_ITERATIONS: u32 = 10_000_000;
+
+const KEYSTORE_VERSION: u32 = 1;
+const KDF_NAME: &str = "pbkdf2-hmac-sha256";
+const CIPHER_NAME: &str = "aes-256-gcm-siv";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+/// Key derivation parameters of a [`Keystore`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeystoreKdf {
+    pub name: String,
+    pub iterations: u32,
+    /// Base64-encoded salt
+    pub salt: String,
+}
+
+/// Cipher parameters of a [`Keystore`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeystoreCipher {
+    pub name: String,
+    /// Base64-encoded nonce
+    pub nonce: String,
+}
+
+/// Password-encrypted keypair, stored as JSON.
+///
+/// The 64-byte keypair is encrypted with AES-256-GCM-SIV under a key derived
+/// from the password with PBKDF2-HMAC-SHA256. The public key is stored in the
+/// clear so the file can be identified without the password.
+///
+/// ```rust,ignore
+/// wallet.write_keystore_file("payer.keystore.json", "correct horse")?;
+/// let wallet = Wallet::from_keystore_file("payer.keystore.json", "correct horse")?;
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub pubkey: String,
+    pub kdf: KeystoreKdf,
+    pub cipher: KeystoreCipher,
+    /// Base64-encoded encrypted keypair
+    pub ciphertext: String,
+}
+
+impl Keystore {
+    /// Encrypt a keypair with [`KEYSTORE_PBKDF2_ITERATIONS`] rounds of key derivation
+    pub fn encrypt(keypair: &Keypair, password: &str) -> Result<Self, X402Error> {
+        Self::encrypt_with_iterations(keypair, password, KEYSTORE_PBKDF2_ITERATIONS)
+    }
+
+    /// Encrypt a keypair with a custom number of key derivation rounds, at most
+    /// [`MAX_KEYSTORE_PBKDF2_ITERATIONS`]
+    pub fn encrypt_with_iterations(
+        keypair: &Keypair,
+        password: &str,
+        iterations: u32,
+    ) -> Result<Self, X402Error> {
+        let mut salt = [0u8; SALT_LENGTH];
+        let mut nonce = [0u8; NONCE_LENGTH];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+        let cipher = Self::cipher(password, &salt, iterations)?;
+        let secret = Zeroizing::new(keypair.to_bytes());
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), secret.as_slice())
+            .map_err(|_| X402Error::WalletError("Failed to encrypt keypair".to_string()))?;
+
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            pubkey: keypair.pubkey().to_string(),
+            kdf: KeystoreKdf {
+                name: KDF_NAME.to_string(),
+                iterations,
+                salt: STANDARD.encode(salt),
+            },
+            cipher: KeystoreCipher {
+                name: CIPHER_NAME.to_string(),
+                nonce: STANDARD.encode(nonce),
+            },
+            ciphertext: STANDARD.encode(ciphertext),
+        })
+    }
+
+    /// Decrypt the keypair; fails on a wrong password or a tampered file
+    pub fn decrypt(&self, password: &str) -> Result<Keypair, X402Error> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(X402Error::WalletError(format!(
+                "Unsupported keystore version {}",
+                self.version
+            )));
+        }
+        if self.kdf.name != KDF_NAME || self.cipher.name != CIPHER_NAME {
+            return Err(X402Error::WalletError(format!(
+                "Unsupported keystore algorithms {} / {}",
+                self.kdf.name, self.cipher.name
+            )));
+        }
+
+        let decode = |field: &str, value: &str| {
+            STANDARD
+                .decode(value)
+                .map_err(|e| X402Error::WalletError(format!("Invalid keystore {}: {}", field, e)))
+        };
+        let salt = decode("salt", &self.kdf.salt)?;
+        let nonce = decode("nonce", &self.cipher.nonce)?;
+        let ciphertext = decode("ciphertext", &self.ciphertext)?;
+        if nonce.len() != NONCE_LENGTH {
+            return Err(X402Error::WalletError("Invalid keystore nonce length".to_string()));
+        }
+
+        let cipher = Self::cipher(password, &salt, self.kdf.iterations)?;
+        let bytes = Zeroizing::new(
+            cipher
+                .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+                .map_err(|_| {
+                    X402Error::WalletError("Wrong password or corrupted keystore".to_string())
+                })?,
+        );
+        let keypair = Keypair::try_from(bytes.as_slice())
+            .map_err(|e| X402Error::WalletError(format!("Invalid keystore keypair: {}", e)))?;
+
+        if keypair.pubkey().to_string() != self.pubkey {
+            return Err(X402Error::WalletError(format!(
+                "Keystore holds {} but is labelled {}",
+                keypair.pubkey(),
+                self.pubkey
+            )));
+        }
+        Ok(keypair)
+    }
+
+    fn cipher(password: &str, salt: &[u8], iterations: u32) -> Result<Aes256GcmSiv, X402Error> {
+        if iterations == 0 || iterations > MAX_KEYSTORE_PBKDF2_ITERATIONS {
+            return Err(X402Error::WalletError(format!(
+                "Invalid keystore iterations {}, expected 1 to {}",
+                iterations, MAX_KEYSTORE_PBKDF2_ITERATIONS
+            )));
+        }
+        let mut key = Zeroizing::new([0u8; 32]);
+        pbkdf2::pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, iterations, key.as_mut());
+        Aes256GcmSiv::new_from_slice(key.as_ref())
+            .map_err(|_| X402Error::WalletError("Invalid keystore key".to_string()))
+    }
+}
@@ -0,0 +1,469 @@
+use super::rpc::{SolanaRpc, TransactionSimulation};
+use super::transaction::{COMPUTE_BUDGET_PROGRAM_ID, LAMPORTS_PER_SIGNATURE, MEMO_PROGRAM_ID};
+use crate::error::X402Error;
+use async_trait::async_trait;
+use solana_sdk::{
+    account::Account,
+    hash::Hash,
+    instruction::InstructionError,
+    message::compiled_instruction::CompiledInstruction,
+    pubkey::Pubkey,
+    sanitize::Sanitize,
+    signature::Signature,
+    transaction::{Transaction as SolanaTransaction, TransactionError},
+};
+use solana_system_interface::program as system_program;
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::instruction::TokenInstruction;
+use spl_token::solana_program::program_option::COption;
+use spl_token::solana_program::program_pack::Pack;
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Rent-exempt balance of an SPL token account
+const TOKEN_ACCOUNT_RENT: u64 = 2_039_280;
+
+/// Rent-exempt balance of an SPL mint
+const MINT_RENT: u64 = 1_461_600;
+
+/// System program `Transfer` discriminant (bincode, little-endian `u32`)
+const SYSTEM_TRANSFER: [u8; 4] = [2, 0, 0, 0];
+
+/// An SPL token account held by the ledger
+#[derive(Debug, Clone)]
+struct TokenBalance {
+    mint: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+}
+
+/// Balances that transactions read and write
+#[derive(Debug, Clone, Default)]
+struct Balances {
+    lamports: HashMap<Pubkey, u64>,
+    /// Mint address to decimals
+    mints: HashMap<Pubkey, u8>,
+    token_accounts: HashMap<Pubkey, TokenBalance>,
+}
+
+#[derive(Debug)]
+struct LedgerState {
+    balances: Balances,
+    blockhash: Hash,
+    signatures: HashMap<Signature, Result<(), TransactionError>>,
+}
+
+/// Failure of a single instruction, with the log line a validator would print
+type InstructionFailure = (InstructionError, Option<String>);
+
+/// In-memory [`SolanaRpc`] that tracks lamport and SPL token balances.
+///
+/// Transactions are checked and applied immediately: System `transfer`, SPL
+/// `transfer_checked`, associated token account creation, memo and Compute
+/// Budget instructions are supported, and a fee of [`LAMPORTS_PER_SIGNATURE`]
+/// per signature is charged to the fee payer. Only the latest blockhash is
+/// valid; call [`advance_blockhash`](Self::advance_blockhash) to expire it.
+///
+/// ```rust,ignore
+/// let ledger = Arc::new(InMemoryLedger::new());
+/// ledger.airdrop(&payer.public_key(), 1_000_000_000);
+/// let fetcher = Fetcher::new(payer, None).with_rpc(ledger.clone());
+/// ```
+#[derive(Debug)]
+pub struct InMemoryLedger {
+    state: Mutex<LedgerState>,
+}
+
+impl InMemoryLedger {
+    /// Create an empty ledger
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(LedgerState {
+                balances: Balances::default(),
+                blockhash: Hash::new_unique(),
+                signatures: HashMap::new(),
+            }),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, LedgerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Credit lamports to an account
+    pub fn airdrop(&self, pubkey: &Pubkey, lamports: u64) {
+        *self.state().balances.lamports.entry(*pubkey).or_default() += lamports;
+    }
+
+    /// Register an SPL token mint
+    pub fn create_mint(&self, mint: &Pubkey, decimals: u8) {
+        let mut state = self.state();
+        state.balances.mints.insert(*mint, decimals);
+        state.balances.lamports.insert(*mint, MINT_RENT);
+    }
+
+    /// Mint tokens to `owner`'s associated token account, creating it if needed.
+    ///
+    /// Returns the associated token account address.
+    pub fn mint_to(&self, owner: &Pubkey, mint: &Pubkey, amount: u64) -> Result<Pubkey, X402Error> {
+        let mut state = self.state();
+        if !state.balances.mints.contains_key(mint) {
+            return Err(X402Error::NotFound(format!("Mint {} does not exist", mint)));
+        }
+        let ata = get_associated_token_address(owner, mint);
+        state.balances.lamports.entry(ata).or_insert(TOKEN_ACCOUNT_RENT);
+        state
+            .balances
+            .token_accounts
+            .entry(ata)
+            .or_insert(TokenBalance {
+                mint: *mint,
+                owner: *owner,
+                amount: 0,
+            })
+            .amount += amount;
+        Ok(ata)
+    }
+
+    /// Lamport balance of an account
+    pub fn balance(&self, pubkey: &Pubkey) -> u64 {
+        self.state().balances.lamports.get(pubkey).copied().unwrap_or(0)
+    }
+
+    /// Token balance of `owner`'s associated token account for `mint`
+    pub fn token_balance(&self, owner: &Pubkey, mint: &Pubkey) -> u64 {
+        let ata = get_associated_token_address(owner, mint);
+        self.state()
+            .balances
+            .token_accounts
+            .get(&ata)
+            .map_or(0, |account| account.amount)
+    }
+
+    /// Replace the latest blockhash, expiring transactions signed with the old one
+    pub fn advance_blockhash(&self) {
+        self.state().blockhash = Hash::new_unique();
+    }
+
+    /// Run a transaction against a copy of the balances.
+    ///
+    /// Returns the updated balances on success, or the transaction error; logs are
+    /// returned either way.
+    fn execute(
+        state: &LedgerState,
+        transaction: &SolanaTransaction,
+        check_signatures: bool,
+    ) -> (Result<Balances, TransactionError>, Vec<String>) {
+        let mut logs = Vec::new();
+        if transaction.sanitize().is_err() {
+            return (Err(TransactionError::SanitizeFailure), logs);
+        }
+        if transaction.message.recent_blockhash != state.blockhash {
+            return (Err(TransactionError::BlockhashNotFound), logs);
+        }
+        if check_signatures && transaction.verify().is_err() {
+            return (Err(TransactionError::SignatureFailure), logs);
+        }
+
+        let mut balances = state.balances.clone();
+        let fee_payer = transaction.message.account_keys[0];
+        let fee = LAMPORTS_PER_SIGNATURE * transaction.message.header.num_required_signatures as u64;
+        match balances.lamports.get_mut(&fee_payer) {
+            None | Some(0) => return (Err(TransactionError::AccountNotFound), logs),
+            Some(lamports) if *lamports < fee => {
+                return (Err(TransactionError::InsufficientFundsForFee), logs)
+            }
+            Some(lamports) => *lamports -= fee,
+        }
+
+        for (index, ix) in transaction.message.instructions.iter().enumerate() {
+            let program = transaction.message.account_keys[ix.program_id_index as usize];
+            logs.push(format!("Program {} invoke [1]", program));
+            match Self::process(&mut balances, transaction, ix, &program) {
+                Ok(()) => logs.push(format!("Program {} success", program)),
+                Err((error, log)) => {
+                    logs.extend(log);
+                    logs.push(format!("Program {} failed: {}", program, error));
+                    return (Err(TransactionError::InstructionError(index as u8, error)), logs);
+                }
+            }
+        }
+
+        (Ok(balances), logs)
+    }
+
+    /// Apply one instruction to the balances
+    fn process(
+        balances: &mut Balances,
+        transaction: &SolanaTransaction,
+        ix: &CompiledInstruction,
+        program: &Pubkey,
+    ) -> Result<(), InstructionFailure> {
+        let message = &transaction.message;
+        let account = |position: usize| -> Result<(Pubkey, bool), InstructionFailure> {
+            let index = *ix
+                .accounts
+                .get(position)
+                .ok_or((InstructionError::MissingAccount, None))? as usize;
+            Ok((message.account_keys[index], message.is_signer(index)))
+        };
+
+        if *program == MEMO_PROGRAM_ID || *program == COMPUTE_BUDGET_PROGRAM_ID {
+            return Ok(());
+        }
+
+        if *program == system_program::ID {
+            let lamports = ix
+                .data
+                .strip_prefix(&SYSTEM_TRANSFER)
+                .and_then(|rest| <[u8; 8]>::try_from(rest).ok())
+                .map(u64::from_le_bytes)
+                .ok_or((InstructionError::InvalidInstructionData, None))?;
+            let (from, from_signed) = account(0)?;
+            let (to, _) = account(1)?;
+            if !from_signed {
+                return Err((InstructionError::MissingRequiredSignature, None));
+            }
+            return Self::move_lamports(balances, &from, &to, lamports);
+        }
+
+        if *program == spl_associated_token_account::ID {
+            // Create (empty data or 0) and CreateIdempotent (1)
+            let idempotent = match ix.data.as_slice() {
+                [] | [0] => false,
+                [1] => true,
+                _ => return Err((InstructionError::InvalidInstructionData, None)),
+            };
+            let (funding, _) = account(0)?;
+            let (ata, _) = account(1)?;
+            let (wallet, _) = account(2)?;
+            let (mint, _) = account(3)?;
+            if ata != get_associated_token_address(&wallet, &mint) {
+                return Err((InstructionError::InvalidSeeds, None));
+            }
+            if !balances.mints.contains_key(&mint) {
+                return Err((InstructionError::InvalidAccountData, None));
+            }
+            if balances.token_accounts.contains_key(&ata) {
+                return if idempotent {
+                    Ok(())
+                } else {
+                    Err((InstructionError::IllegalOwner, None))
+                };
+            }
+            Self::move_lamports(balances, &funding, &ata, TOKEN_ACCOUNT_RENT)?;
+            balances.token_accounts.insert(
+                ata,
+                TokenBalance {
+                    mint,
+                    owner: wallet,
+                    amount: 0,
+                },
+            );
+            return Ok(());
+        }
+
+        if *program == spl_token::ID {
+            let Ok(TokenInstruction::TransferChecked { amount, decimals }) =
+                TokenInstruction::unpack(&ix.data)
+            else {
+                return Err((InstructionError::InvalidInstructionData, None));
+            };
+            let (source, _) = account(0)?;
+            let (mint, _) = account(1)?;
+            let (destination, _) = account(2)?;
+            let (authority, authority_signed) = account(3)?;
+
+            let (Some(source_account), Some(destination_account)) = (
+                balances.token_accounts.get(&source),
+                balances.token_accounts.get(&destination),
+            ) else {
+                return Err((InstructionError::InvalidAccountData, None));
+            };
+            let Some(mint_decimals) = balances.mints.get(&mint) else {
+                return Err((InstructionError::InvalidAccountData, None));
+            };
+            // TokenError::MintMismatch
+            if source_account.mint != mint || destination_account.mint != mint {
+                return Err((InstructionError::Custom(3), Some("Program log: Error: Account not associated with this Mint".to_string())));
+            }
+            // TokenError::MintDecimalsMismatch
+            if *mint_decimals != decimals {
+                return Err((InstructionError::Custom(18), None));
+            }
+            // TokenError::OwnerMismatch
+            if source_account.owner != authority {
+                return Err((InstructionError::Custom(4), Some("Program log: Error: owner does not match".to_string())));
+            }
+            if !authority_signed {
+                return Err((InstructionError::MissingRequiredSignature, None));
+            }
+            // TokenError::InsufficientFunds
+            if source_account.amount < amount {
+                return Err((InstructionError::Custom(1), Some("Program log: Error: insufficient funds".to_string())));
+            }
+
+            if let Some(source_account) = balances.token_accounts.get_mut(&source) {
+                source_account.amount -= amount;
+            }
+            if let Some(destination_account) = balances.token_accounts.get_mut(&destination) {
+                destination_account.amount += amount;
+            }
+            return Ok(());
+        }
+
+        Err((InstructionError::UnsupportedProgramId, None))
+    }
+
+    /// Move lamports between accounts, failing like the System program when short
+    fn move_lamports(
+        balances: &mut Balances,
+        from: &Pubkey,
+        to: &Pubkey,
+        lamports: u64,
+    ) -> Result<(), InstructionFailure> {
+        let available = balances.lamports.get(from).copied().unwrap_or(0);
+        if available < lamports {
+            // SystemError::ResultWithNegativeLamports
+            let log = format!("Transfer: insufficient lamports {}, need {}", available, lamports);
+            return Err((InstructionError::Custom(1), Some(log)));
+        }
+        balances.lamports.insert(*from, available - lamports);
+        *balances.lamports.entry(*to).or_default() += lamports;
+        Ok(())
+    }
+}
+
+impl Default for InMemoryLedger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl SolanaRpc for InMemoryLedger {
+    async fn get_latest_blockhash(&self) -> Result<Hash, X402Error> {
+        Ok(self.state().blockhash)
+    }
+
+    async fn get_account(&self, pubkey: &Pubkey) -> Result<Option<Account>, X402Error> {
+        let state = self.state();
+        let balances = &state.balances;
+        let lamports = balances.lamports.get(pubkey).copied().unwrap_or(0);
+
+        if let Some(token_account) = balances.token_accounts.get(pubkey) {
+            let mut data = vec![0; TokenAccount::LEN];
+            TokenAccount::pack(
+                TokenAccount {
+                    mint: token_account.mint,
+                    owner: token_account.owner,
+                    amount: token_account.amount,
+                    delegate: COption::None,
+                    state: AccountState::Initialized,
+                    is_native: COption::None,
+                    delegated_amount: 0,
+                    close_authority: COption::None,
+                },
+                &mut data,
+            )
+            .map_err(|e| X402Error::InternalError(e.to_string()))?;
+            return Ok(Some(Account {
+                lamports,
+                data,
+                owner: spl_token::ID,
+                executable: false,
+                rent_epoch: 0,
+            }));
+        }
+
+        if let Some(decimals) = balances.mints.get(pubkey) {
+            let supply = balances
+                .token_accounts
+                .values()
+                .filter(|account| account.mint == *pubkey)
+                .map(|account| account.amount)
+                .sum();
+            let mut data = vec![0; Mint::LEN];
+            Mint::pack(
+                Mint {
+                    mint_authority: COption::None,
+                    supply,
+                    decimals: *decimals,
+                    is_initialized: true,
+                    freeze_authority: COption::None,
+                },
+                &mut data,
+            )
+            .map_err(|e| X402Error::InternalError(e.to_string()))?;
+            return Ok(Some(Account {
+                lamports,
+                data,
+                owner: spl_token::ID,
+                executable: false,
+                rent_epoch: 0,
+            }));
+        }
+
+        Ok((lamports > 0).then(|| Account {
+            lamports,
+            data: Vec::new(),
+            owner: system_program::ID,
+            executable: false,
+            rent_epoch: 0,
+        }))
+    }
+
+    async fn get_balance(&self, pubkey: &Pubkey) -> Result<u64, X402Error> {
+        Ok(self.balance(pubkey))
+    }
+
+    async fn simulate_transaction(
+        &self,
+        transaction: &SolanaTransaction,
+    ) -> Result<TransactionSimulation, X402Error> {
+        let (result, logs) = Self::execute(&self.state(), transaction, false);
+        Ok(TransactionSimulation {
+            err: result.err(),
+            logs,
+        })
+    }
+
+    async fn send_transaction(
+        &self,
+        transaction: &SolanaTransaction,
+    ) -> Result<Signature, X402Error> {
+        let mut state = self.state();
+        let signature = transaction.signatures.first().copied().unwrap_or_default();
+        if state.signatures.contains_key(&signature) {
+            return Err(X402Error::SolanaError(format!(
+                "Transaction failed: {}",
+                TransactionError::AlreadyProcessed
+            )));
+        }
+
+        // Like a preflight check: failing transactions are rejected, not recorded
+        let (result, _) = Self::execute(&state, transaction, true);
+        let balances = result.map_err(|e| {
+            X402Error::SolanaError(format!("Transaction simulation failed: {}", e))
+        })?;
+        state.balances = balances;
+        state.signatures.insert(signature, Ok(()));
+        Ok(signature)
+    }
+
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<Result<(), TransactionError>>, X402Error> {
+        Ok(self.state().signatures.get(signature).cloned())
+    }
+
+    async fn send_and_confirm_transaction(
+        &self,
+        transaction: &SolanaTransaction,
+    ) -> Result<Signature, X402Error> {
+        // Transactions are final as soon as they are applied
+        self.send_transaction(transaction).await
+    }
+}
@@ -1,5 +1,21 @@
+mod keystore;
+mod ledger;
+mod remote_signer;
+mod rpc;
+mod signer;
 mod transaction;
 mod wallet;
 
-pub use transaction::{Transaction, TransactionBuilder};
-pub use wallet::{Wallet, create_signer};
\ No newline at end of file
+pub use keystore::{
+    Keystore, KeystoreCipher, KeystoreKdf, KEYSTORE_PBKDF2_ITERATIONS,
+    MAX_KEYSTORE_PBKDF2_ITERATIONS,
+};
+pub use ledger::InMemoryLedger;
+pub use remote_signer::{RemoteSigner, SignRequest, SignResponse};
+pub use rpc::{SolanaRpc, TransactionSimulation};
+pub use signer::PaymentSigner;
+pub use transaction::{
+    AsyncTransactionBuilder, Transaction, TransactionBuilder, COMPUTE_BUDGET_PROGRAM_ID,
+    LAMPORTS_PER_SIGNATURE, MEMO_PROGRAM_ID,
+};
+pub use wallet::{Wallet, create_signer, DEFAULT_DERIVATION_PATH};
@@ -0,0 +1,187 @@
+use super::signer::PaymentSigner;
+use crate::error::X402Error;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long a signing daemon on a Unix socket may take to answer
+const DEFAULT_SIGNER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest reply line accepted from a signing daemon on a Unix socket
+#[cfg(unix)]
+const MAX_RESPONSE_BYTES: u64 = 64 * 1024;
+
+/// Request sent to a signing daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignRequest {
+    /// Account whose key should sign
+    pub pubkey: String,
+    /// Base64-encoded serialized transaction message
+    pub message: String,
+}
+
+/// Reply from a signing daemon: a base58 signature, or why it refused to sign
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SignResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Transport {
+    Http { client: reqwest::Client, url: String },
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// [`PaymentSigner`] that asks a local signing daemon for signatures.
+///
+/// The daemon receives a [`SignRequest`] and answers with a [`SignResponse`]:
+///
+/// - over HTTP, as a JSON `POST` to `{url}/sign`
+/// - over a Unix socket, as one line of JSON per request and per response,
+///   of at most 64 KiB and within the [timeout](Self::with_timeout)
+///
+/// The key never enters this process. Returned signatures are checked against
+/// the account and message before they are used.
+///
+/// ```rust,ignore
+/// let signer = RemoteSigner::http("http://127.0.0.1:7000", payer_pubkey);
+/// let fetcher = Fetcher::new(signer, None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    pubkey: Pubkey,
+    transport: Transport,
+    timeout: Duration,
+}
+
+impl RemoteSigner {
+    /// Sign through an HTTP daemon at `url` holding the key for `pubkey`
+    pub fn http(url: impl Into<String>, pubkey: Pubkey) -> Self {
+        Self::http_with_client(reqwest::Client::new(), url, pubkey)
+    }
+
+    /// Sign through an HTTP daemon using a preconfigured client (e.g. with auth headers)
+    pub fn http_with_client(client: reqwest::Client, url: impl Into<String>, pubkey: Pubkey) -> Self {
+        let url = url.into().trim_end_matches('/').to_string();
+        Self {
+            pubkey,
+            transport: Transport::Http { client, url },
+            timeout: DEFAULT_SIGNER_TIMEOUT,
+        }
+    }
+
+    /// Sign through a daemon listening on the Unix socket at `path`
+    #[cfg(unix)]
+    pub fn unix(path: impl Into<PathBuf>, pubkey: Pubkey) -> Self {
+        Self {
+            pubkey,
+            transport: Transport::Unix(path.into()),
+            timeout: DEFAULT_SIGNER_TIMEOUT,
+        }
+    }
+
+    /// Give up on a Unix socket daemon that has not answered within `timeout`
+    /// (30 seconds by default).
+    ///
+    /// HTTP daemons use the timeouts of their `reqwest::Client`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn request(&self, request: &SignRequest) -> Result<SignResponse, X402Error> {
+        match &self.transport {
+            Transport::Http { client, url } => {
+                let response = client
+                    .post(format!("{}/sign", url))
+                    .json(request)
+                    .send()
+                    .await
+                    .map_err(|e| X402Error::NetworkError(format!("Signer unreachable: {}", e)))?;
+                // Refusals come back as a SignResponse with an error, whatever the status
+                response
+                    .json()
+                    .await
+                    .map_err(|e| X402Error::DeserializationError(format!("Invalid signer response: {}", e)))
+            }
+            #[cfg(unix)]
+            Transport::Unix(path) => tokio::time::timeout(self.timeout, Self::request_unix(path, request))
+                .await
+                .map_err(|_| {
+                    X402Error::NetworkError(format!("Signer did not answer within {:?}", self.timeout))
+                })?,
+        }
+    }
+
+    #[cfg(unix)]
+    async fn request_unix(path: &Path, request: &SignRequest) -> Result<SignResponse, X402Error> {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+        let stream = tokio::net::UnixStream::connect(path)
+            .await
+            .map_err(|e| X402Error::NetworkError(format!("Signer unreachable: {}", e)))?;
+        let (reader, mut writer) = stream.into_split();
+
+        let mut line = serde_json::to_string(request)
+            .map_err(|e| X402Error::SerializationError(e.to_string()))?;
+        line.push('\n');
+        writer
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| X402Error::NetworkError(format!("Signer write failed: {}", e)))?;
+
+        let mut reply = String::new();
+        let read = BufReader::new(reader.take(MAX_RESPONSE_BYTES))
+            .read_line(&mut reply)
+            .await
+            .map_err(|e| X402Error::NetworkError(format!("Signer read failed: {}", e)))?;
+        if read as u64 == MAX_RESPONSE_BYTES && !reply.ends_with('\n') {
+            return Err(X402Error::DeserializationError(format!(
+                "Signer response is longer than {} bytes",
+                MAX_RESPONSE_BYTES
+            )));
+        }
+        serde_json::from_str(&reply)
+            .map_err(|e| X402Error::DeserializationError(format!("Invalid signer response: {}", e)))
+    }
+}
+
+#[async_trait]
+impl PaymentSigner for RemoteSigner {
+    fn address(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    async fn sign_payment(&self, message: &[u8]) -> Result<Signature, X402Error> {
+        let request = SignRequest {
+            pubkey: self.pubkey.to_string(),
+            message: STANDARD.encode(message),
+        };
+        let response = self.request(&request).await?;
+
+        if let Some(error) = response.error {
+            return Err(X402Error::Unauthorized(format!("Signer refused: {}", error)));
+        }
+        let signature: Signature = response
+            .signature
+            .ok_or_else(|| X402Error::InvalidSignature("Signer returned no signature".to_string()))?
+            .parse()
+            .map_err(|e| X402Error::InvalidSignature(format!("Invalid signature from signer: {}", e)))?;
+
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(X402Error::InvalidSignature(format!(
+                "Signer returned a signature that does not verify for {}",
+                self.pubkey
+            )));
+        }
+        Ok(signature)
+    }
+}
@@ -0,0 +1,179 @@
+use super::transaction::LAMPORTS_PER_SIGNATURE;
+use crate::error::X402Error;
+use async_trait::async_trait;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::{
+    account::Account,
+    hash::Hash,
+    message::Message,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Signature,
+    transaction::{Transaction as SolanaTransaction, TransactionError},
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::solana_program::program_pack::Pack;
+use spl_token::state::Account as TokenAccount;
+
+/// Outcome of simulating a transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionSimulation {
+    /// Why the transaction would fail, or `None` if it would succeed
+    pub err: Option<TransactionError>,
+    /// Program log lines
+    pub logs: Vec<String>,
+}
+
+/// The Solana RPC calls used by the client, server and facilitator.
+///
+/// Implemented for the non-blocking [`RpcClient`] and by
+/// [`InMemoryLedger`](super::InMemoryLedger) for offline tests.
+#[async_trait]
+pub trait SolanaRpc: Send + Sync {
+    /// Latest blockhash to sign new transactions with
+    async fn get_latest_blockhash(&self) -> Result<Hash, X402Error>;
+
+    /// Look up an account; `None` if it does not exist
+    async fn get_account(&self, pubkey: &Pubkey) -> Result<Option<Account>, X402Error>;
+
+    /// Lamport balance of an account (0 if it does not exist)
+    async fn get_balance(&self, pubkey: &Pubkey) -> Result<u64, X402Error>;
+
+    /// Token balance of `owner`'s associated token account for `mint`, or `None`
+    /// if that account does not exist
+    async fn get_token_balance(&self, owner: &Pubkey, mint: &Pubkey) -> Result<Option<u64>, X402Error> {
+        let ata = get_associated_token_address(owner, mint);
+        match self.get_account(&ata).await? {
+            Some(account) => TokenAccount::unpack(&account.data)
+                .map(|token| Some(token.amount))
+                .map_err(|e| X402Error::SolanaError(format!("Invalid token account {}: {}", ata, e))),
+            None => Ok(None),
+        }
+    }
+
+    /// Lamports an account with `data_len` bytes needs to be rent exempt.
+    ///
+    /// Defaults to the cluster's default rent parameters.
+    async fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> Result<u64, X402Error> {
+        Ok(Rent::default().minimum_balance(data_len))
+    }
+
+    /// Lamports the fee payer is charged for `message`, including priority fees.
+    ///
+    /// Defaults to [`LAMPORTS_PER_SIGNATURE`] per required signature, without
+    /// priority fees.
+    async fn get_fee_for_message(&self, message: &Message) -> Result<u64, X402Error> {
+        Ok(LAMPORTS_PER_SIGNATURE * u64::from(message.header.num_required_signatures))
+    }
+
+    /// Simulate a transaction without checking signatures or replacing its blockhash
+    async fn simulate_transaction(
+        &self,
+        transaction: &SolanaTransaction,
+    ) -> Result<TransactionSimulation, X402Error>;
+
+    /// Submit a transaction without waiting for confirmation
+    async fn send_transaction(&self, transaction: &SolanaTransaction)
+        -> Result<Signature, X402Error>;
+
+    /// Status of a submitted transaction: `None` if unknown, otherwise its result
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<Result<(), TransactionError>>, X402Error>;
+
+    /// Whether a submitted transaction has been confirmed successfully
+    async fn confirm_transaction(&self, signature: &Signature) -> Result<bool, X402Error> {
+        Ok(matches!(self.get_signature_status(signature).await?, Some(Ok(()))))
+    }
+
+    /// Submit a transaction and wait until it is confirmed
+    async fn send_and_confirm_transaction(
+        &self,
+        transaction: &SolanaTransaction,
+    ) -> Result<Signature, X402Error>;
+}
+
+#[async_trait]
+impl SolanaRpc for RpcClient {
+    async fn get_latest_blockhash(&self) -> Result<Hash, X402Error> {
+        RpcClient::get_latest_blockhash(self)
+            .await
+            .map_err(|e| X402Error::SolanaError(format!("Failed to get blockhash: {}", e)))
+    }
+
+    async fn get_account(&self, pubkey: &Pubkey) -> Result<Option<Account>, X402Error> {
+        let response = self
+            .get_account_with_commitment(pubkey, self.commitment())
+            .await
+            .map_err(|e| X402Error::SolanaError(format!("Failed to get account: {}", e)))?;
+        Ok(response.value)
+    }
+
+    async fn get_balance(&self, pubkey: &Pubkey) -> Result<u64, X402Error> {
+        RpcClient::get_balance(self, pubkey)
+            .await
+            .map_err(|e| X402Error::SolanaError(format!("Failed to get balance: {}", e)))
+    }
+
+    async fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> Result<u64, X402Error> {
+        RpcClient::get_minimum_balance_for_rent_exemption(self, data_len)
+            .await
+            .map_err(|e| X402Error::SolanaError(format!("Failed to get rent exemption: {}", e)))
+    }
+
+    async fn get_fee_for_message(&self, message: &Message) -> Result<u64, X402Error> {
+        RpcClient::get_fee_for_message(self, message)
+            .await
+            .map_err(|e| X402Error::SolanaError(format!("Failed to get fee: {}", e)))
+    }
+
+    async fn simulate_transaction(
+        &self,
+        transaction: &SolanaTransaction,
+    ) -> Result<TransactionSimulation, X402Error> {
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: false,
+            ..Default::default()
+        };
+        let simulation = self
+            .simulate_transaction_with_config(transaction, config)
+            .await
+            .map_err(|e| X402Error::SolanaError(format!("Simulation request failed: {}", e)))?
+            .value;
+
+        Ok(TransactionSimulation {
+            err: simulation.err.map(Into::into),
+            logs: simulation.logs.unwrap_or_default(),
+        })
+    }
+
+    async fn send_transaction(
+        &self,
+        transaction: &SolanaTransaction,
+    ) -> Result<Signature, X402Error> {
+        RpcClient::send_transaction(self, transaction)
+            .await
+            .map_err(|e| X402Error::SolanaError(format!("Transaction failed: {}", e)))
+    }
+
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<Result<(), TransactionError>>, X402Error> {
+        RpcClient::get_signature_status(self, signature)
+            .await
+            .map_err(|e| X402Error::SolanaError(format!("Failed to get signature status: {}", e)))
+    }
+
+    async fn send_and_confirm_transaction(
+        &self,
+        transaction: &SolanaTransaction,
+    ) -> Result<Signature, X402Error> {
+        RpcClient::send_and_confirm_transaction(self, transaction)
+            .await
+            .map_err(|e| X402Error::SolanaError(format!("Transaction failed: {}", e)))
+    }
+}
@@ -0,0 +1,67 @@
+use crate::error::X402Error;
+use async_trait::async_trait;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    transaction::Transaction as SolanaTransaction,
+};
+
+/// Identity that authorizes x402 payments.
+///
+/// [`Fetcher`](crate::client::Fetcher) and
+/// [`AsyncTransactionBuilder`](super::AsyncTransactionBuilder) only need a
+/// public key and a signature over the serialized transaction message, so the
+/// key does not have to live in the process. Every Solana [`Signer`] (such as a
+/// [`Keypair`](solana_sdk::signature::Keypair) or [`Wallet`](super::Wallet))
+/// implements this trait, [`RemoteSigner`](super::RemoteSigner) forwards to a
+/// signing daemon, and custody services or KMS proxies can implement it
+/// directly. Share one identity across fetchers and tasks with
+/// `Arc<dyn PaymentSigner>`.
+#[async_trait]
+pub trait PaymentSigner: Send + Sync {
+    /// Account that pays and signs the transfer
+    fn address(&self) -> Pubkey;
+
+    /// Sign a serialized transaction message
+    async fn sign_payment(&self, message: &[u8]) -> Result<Signature, X402Error>;
+}
+
+#[async_trait]
+impl<T: Signer + Send + Sync> PaymentSigner for T {
+    fn address(&self) -> Pubkey {
+        self.pubkey()
+    }
+
+    async fn sign_payment(&self, message: &[u8]) -> Result<Signature, X402Error> {
+        self.try_sign_message(message)
+            .map_err(|e| X402Error::InvalidSignature(format!("Failed to sign: {}", e)))
+    }
+}
+
+/// Index of `pubkey` among the transaction's required signers
+pub(crate) fn signer_position(
+    transaction: &SolanaTransaction,
+    pubkey: &Pubkey,
+) -> Result<usize, X402Error> {
+    let required = transaction.message.header.num_required_signatures as usize;
+    transaction
+        .message
+        .account_keys
+        .iter()
+        .take(required)
+        .position(|key| key == pubkey)
+        .ok_or_else(|| {
+            X402Error::InvalidSignature(format!("{} is not a signer of this transaction", pubkey))
+        })
+}
+
+/// Add `signer`'s signature to a transaction, leaving other signatures untouched
+pub(crate) async fn partial_sign(
+    transaction: &mut SolanaTransaction,
+    signer: &dyn PaymentSigner,
+) -> Result<(), X402Error> {
+    let position = signer_position(transaction, &signer.address())?;
+    let signature = signer.sign_payment(&transaction.message_data()).await?;
+    transaction.signatures[position] = signature;
+    Ok(())
+}
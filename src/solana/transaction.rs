@@ -1,19 +1,177 @@
+use super::rpc::SolanaRpc;
+use super::signer::{partial_sign, signer_position, PaymentSigner};
 use crate::error::X402Error;
+use solana_client::nonblocking::rpc_client::RpcClient as AsyncRpcClient;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
+    hash::Hash,
     instruction::{AccountMeta, Instruction},
     message::Message,
+    pubkey,
     pubkey::Pubkey,
     signature::{Keypair, Signature, Signer},
     transaction::Transaction as SolanaTransaction,
 };
 use solana_system_interface::instruction as system_instruction;
+use spl_token::solana_program::program_pack::Pack;
 use std::str::FromStr;
+use std::sync::Arc;
 use base64::{Engine as _, engine::general_purpose::STANDARD};
 
-/// Transaction builder for Solana payments
+/// SPL Memo program, used to commit payments to a server-issued nonce
+pub const MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+/// Compute Budget program, used to set priority fees
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey = pubkey!("ComputeBudget111111111111111111111111111111");
+
+/// Base fee charged per transaction signature, before priority fees
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// Payment settings shared by [`TransactionBuilder`] and [`AsyncTransactionBuilder`]
+#[derive(Debug, Clone, Default)]
+struct PaymentOptions {
+    memo: Option<String>,
+    fee_payer: Option<Pubkey>,
+}
+
+impl PaymentOptions {
+    /// Build an unsigned transaction paid for by the fee payer (or `from`)
+    fn unsigned_transaction(
+        &self,
+        instructions: &[Instruction],
+        from: &Pubkey,
+        recent_blockhash: Hash,
+    ) -> SolanaTransaction {
+        let fee_payer = self.fee_payer.unwrap_or(*from);
+        let mut message = Message::new(instructions, Some(&fee_payer));
+        message.recent_blockhash = recent_blockhash;
+        SolanaTransaction::new_unsigned(message)
+    }
+
+    /// Build a transaction and sign it as `from` with a local key
+    fn signed_transaction(
+        &self,
+        instructions: &[Instruction],
+        from: &dyn Signer,
+        recent_blockhash: Hash,
+    ) -> Result<SolanaTransaction, X402Error> {
+        let mut transaction = self.unsigned_transaction(instructions, &from.pubkey(), recent_blockhash);
+        let position = signer_position(&transaction, &from.pubkey())?;
+        transaction.signatures[position] = from
+            .try_sign_message(&transaction.message_data())
+            .map_err(|e| X402Error::InvalidSignature(format!("Failed to sign: {}", e)))?;
+        Ok(transaction)
+    }
+
+    /// Build a transaction and sign it as `from` through a [`PaymentSigner`]
+    async fn signed_transaction_async(
+        &self,
+        instructions: &[Instruction],
+        from: &dyn PaymentSigner,
+        recent_blockhash: Hash,
+    ) -> Result<SolanaTransaction, X402Error> {
+        let mut transaction = self.unsigned_transaction(instructions, &from.address(), recent_blockhash);
+        partial_sign(&mut transaction, from).await?;
+        Ok(transaction)
+    }
+
+    /// Memo instruction signed by `signer`, if a memo is configured
+    fn memo_instruction(&self, signer: &Pubkey) -> Option<Instruction> {
+        self.memo.as_ref().map(|memo| {
+            Instruction::new_with_bytes(
+                MEMO_PROGRAM_ID,
+                memo.as_bytes(),
+                vec![AccountMeta::new_readonly(*signer, true)],
+            )
+        })
+    }
+
+    /// Instructions for a SOL transfer
+    fn sol_payment_instructions(&self, from: &Pubkey, to: &Pubkey, amount_lamports: u64) -> Vec<Instruction> {
+        let mut instructions: Vec<Instruction> = self.memo_instruction(from).into_iter().collect();
+        // Solana 3.0: Use system_instruction from solana_system_interface crate
+        instructions.push(system_instruction::transfer(from, to, amount_lamports));
+        instructions
+    }
+
+    /// Instructions for an SPL token payment, creating missing associated token accounts
+    #[allow(clippy::too_many_arguments)]
+    fn spl_payment_instructions(
+        &self,
+        payer: &Pubkey,
+        to_owner: &Pubkey,
+        token_mint: &Pubkey,
+        amount: u64,
+        decimals: u8,
+        sender_ata_exists: bool,
+        receiver_ata_exists: bool,
+    ) -> Result<Vec<Instruction>, X402Error> {
+        let token_program_id = spl_token::ID;
+
+        // 1. Derive associated token accounts using Solana 3.0 compatible method
+        let sender_ata = spl_associated_token_account::get_associated_token_address(
+            payer,
+            token_mint,
+        );
+        let receiver_ata = spl_associated_token_account::get_associated_token_address(
+            to_owner,
+            token_mint,
+        );
+
+        let mut instructions: Vec<Instruction> = self.memo_instruction(payer).into_iter().collect();
+
+        // 2. Check if sender has the token account; if missing, create it
+        if !sender_ata_exists {
+            // Create ATA instruction for sender
+            let create_sender_ata_ix = spl_associated_token_account::instruction::create_associated_token_account(
+                payer,             // funding account
+                payer,             // wallet address (sender)
+                token_mint,        // SPL Token mint
+                &token_program_id, // Token program ID
+            );
+            instructions.push(create_sender_ata_ix);
+        }
+
+        // 3. Check if receiver's ATA exists, create if not
+        if !receiver_ata_exists {
+            // Create ATA instruction for receiver
+            let create_ata_ix = spl_associated_token_account::instruction::create_associated_token_account(
+                payer,             // funding account
+                to_owner,          // wallet address (receiver)
+                token_mint,        // SPL Token mint
+                &token_program_id, // Token program ID
+            );
+            instructions.push(create_ata_ix);
+        }
+
+        // 4. Create transfer instruction
+        let transfer_ix = spl_token::instruction::transfer_checked(
+            &token_program_id, // token program
+            &sender_ata,       // source
+            token_mint,        // mint
+            &receiver_ata,     // destination
+            payer,             // authority
+            &[],               // signers (empty because authority will sign)
+            amount,            // amount
+            decimals,          // decimals
+        )
+        .map_err(|e| X402Error::SolanaError(format!("Failed to create transfer instruction: {}", e)))?;
+
+        instructions.push(transfer_ix);
+        Ok(instructions)
+    }
+}
+
+/// Transaction builder for Solana payments.
+///
+/// Uses the blocking RPC client; inside an async runtime prefer
+/// [`AsyncTransactionBuilder`]. Payments are signed with a local key (any
+/// Solana [`Signer`], such as a `Keypair`); to sign through a
+/// [`PaymentSigner`] such as a
+/// [`RemoteSigner`](super::RemoteSigner), use [`AsyncTransactionBuilder`].
 pub struct TransactionBuilder {
     rpc_client: RpcClient,
+    options: PaymentOptions,
 }
 
 impl TransactionBuilder {
@@ -21,13 +179,40 @@ impl TransactionBuilder {
     pub fn new(rpc_url: &str) -> Self {
         Self {
             rpc_client: RpcClient::new(rpc_url.to_string()),
+            options: PaymentOptions::default(),
         }
     }
 
+    /// Add a memo instruction (e.g. a server-issued nonce) to every payment built
+    pub fn with_memo(mut self, memo: impl Into<String>) -> Self {
+        self.options.memo = Some(memo.into());
+        self
+    }
+
+    /// Let another account (e.g. the facilitator) pay the network fee.
+    ///
+    /// Transactions are then only partially signed by the payer; the fee payer
+    /// adds its signature when it settles the payment.
+    pub fn with_fee_payer(mut self, fee_payer: Pubkey) -> Self {
+        self.options.fee_payer = Some(fee_payer);
+        self
+    }
+
+    /// Extract the first memo committed to by a transaction
+    pub fn find_memo(transaction: &SolanaTransaction) -> Option<String> {
+        let keys = &transaction.message.account_keys;
+        transaction.message.instructions.iter().find_map(|ix| {
+            let program = keys.get(ix.program_id_index as usize)?;
+            (*program == MEMO_PROGRAM_ID)
+                .then(|| String::from_utf8(ix.data.clone()).ok())
+                .flatten()
+        })
+    }
+
     /// Create a payment transaction (SOL transfer)
     pub fn create_payment_transaction(
         &self,
-        from: &Keypair,
+        from: &dyn Signer,
         to: &Pubkey,
         amount_lamports: u64,
     ) -> Result<SolanaTransaction, X402Error> {
@@ -36,12 +221,10 @@ impl TransactionBuilder {
             .get_latest_blockhash()
             .map_err(|e| X402Error::SolanaError(format!("Failed to get blockhash: {}", e)))?;
 
-        // Solana 3.0: Use system_instruction from solana_system_interface crate
-        let instruction = system_instruction::transfer(&from.pubkey(), to, amount_lamports);
-
-        let message = Message::new(&[instruction], Some(&from.pubkey()));
-        let mut transaction = SolanaTransaction::new_unsigned(message);
-        transaction.sign(&[from], recent_blockhash);
+        let instructions = self
+            .options
+            .sol_payment_instructions(&from.pubkey(), to, amount_lamports);
+        let transaction = self.options.signed_transaction(&instructions, from, recent_blockhash)?;
 
         Ok(transaction)
     }
@@ -49,7 +232,7 @@ impl TransactionBuilder {
     /// Create an SPL token transfer transaction
     pub fn create_token_transfer_transaction(
         &self,
-        from: &Keypair,
+        from: &dyn Signer,
         to_token_account: &Pubkey,
         from_token_account: &Pubkey,
         _amount: u64,
@@ -71,9 +254,11 @@ impl TransactionBuilder {
             ],
         );
 
-        let message = Message::new(&[instruction], Some(&from.pubkey()));
-        let mut transaction = SolanaTransaction::new_unsigned(message);
-        transaction.sign(&[from], recent_blockhash);
+        let mut instructions: Vec<Instruction> =
+            self.options.memo_instruction(&from.pubkey()).into_iter().collect();
+        instructions.push(instruction);
+
+        let transaction = self.options.signed_transaction(&instructions, from, recent_blockhash)?;
 
         Ok(transaction)
     }
@@ -82,96 +267,36 @@ impl TransactionBuilder {
     /// This creates a complete token transfer with automatic ATA creation if needed
     pub fn create_spl_token_payment(
         &self,
-        from: &Keypair,
+        from: &dyn Signer,
         to_owner: &Pubkey,
         token_mint: &Pubkey,
         amount: u64,
         decimals: u8,
     ) -> Result<SolanaTransaction, X402Error> {
         let payer = from.pubkey();
-        let token_program_id = spl_token::ID;
-        
-        // 1. Derive associated token accounts using Solana 3.0 compatible method
-        let sender_ata = spl_associated_token_account::get_associated_token_address(
+        let sender_ata = spl_associated_token_account::get_associated_token_address(&payer, token_mint);
+        let receiver_ata =
+            spl_associated_token_account::get_associated_token_address(to_owner, token_mint);
+
+        let instructions = self.options.spl_payment_instructions(
             &payer,
-            token_mint,
-        );
-        let receiver_ata = spl_associated_token_account::get_associated_token_address(
             to_owner,
             token_mint,
-        );
-        
-        println!("  💳 Sender ATA: {}", sender_ata);
-        println!("  💳 Receiver ATA: {}", receiver_ata);
-        
-        let mut instructions: Vec<Instruction> = Vec::new();
-        
-        // 2. Check if sender has the token account; if missing, create it
-        match self.rpc_client.get_account(&sender_ata) {
-            Ok(_) => {
-                println!("  ✓ Sender ATA exists");
-            }
-            Err(_) => {
-                println!("  ⚠️  Sender ATA doesn't exist, creating...");
-
-                // Create ATA instruction for sender
-                let create_sender_ata_ix = spl_associated_token_account::instruction::create_associated_token_account(
-                    &payer,           // funding account
-                    &payer,           // wallet address (sender)
-                    token_mint,       // SPL Token mint
-                    &token_program_id, // Token program ID
-                );
-                instructions.push(create_sender_ata_ix);
-            }
-        }
-        
-        // 3. Check if receiver's ATA exists, create if not
-        match self.rpc_client.get_account(&receiver_ata) {
-            Ok(_) => {
-                println!("  ✓ Receiver ATA exists");
-            }
-            Err(_) => {
-                println!("  ⚠️  Receiver ATA doesn't exist, creating...");
-                
-                // Create ATA instruction for receiver
-                let create_ata_ix = spl_associated_token_account::instruction::create_associated_token_account(
-                    &payer,           // funding account
-                    to_owner,         // wallet address (receiver)
-                    token_mint,       // SPL Token mint
-                    &token_program_id, // Token program ID
-                );
-                instructions.push(create_ata_ix);
-            }
-        }
-        
-        // 4. Create transfer instruction
-        let transfer_ix = spl_token::instruction::transfer_checked(
-            &token_program_id, // token program
-            &sender_ata,       // source
-            token_mint,        // mint
-            &receiver_ata,     // destination
-            &payer,            // authority
-            &[],               // signers (empty because authority will sign)
-            amount,            // amount
-            decimals,          // decimals
-        )
-        .map_err(|e| X402Error::SolanaError(format!("Failed to create transfer instruction: {}", e)))?;
-        
-        instructions.push(transfer_ix);
-        
-        // 5. Get recent blockhash
+            amount,
+            decimals,
+            self.rpc_client.get_account(&sender_ata).is_ok(),
+            self.rpc_client.get_account(&receiver_ata).is_ok(),
+        )?;
+
+        // Get recent blockhash, then create and sign transaction
         let recent_blockhash = self
             .rpc_client
             .get_latest_blockhash()
             .map_err(|e| X402Error::SolanaError(format!("Failed to get blockhash: {}", e)))?;
-        
-        // 6. Create and sign transaction
-        let message = Message::new(&instructions, Some(&payer));
-        let mut transaction = SolanaTransaction::new_unsigned(message);
-        transaction.sign(&[from], recent_blockhash);
-        
+        let transaction = self.options.signed_transaction(&instructions, from, recent_blockhash)?;
+
         println!("  ✅ Token transfer transaction created and signed");
-        
+
         Ok(transaction)
     }
 
@@ -220,6 +345,209 @@ impl TransactionBuilder {
     }
 }
 
+/// Non-blocking counterpart of [`TransactionBuilder`] for use inside async runtimes.
+///
+/// Talks to the network through [`SolanaRpc`], so it can also run against an
+/// [`InMemoryLedger`](super::InMemoryLedger).
+pub struct AsyncTransactionBuilder {
+    rpc: Arc<dyn SolanaRpc>,
+    options: PaymentOptions,
+}
+
+impl AsyncTransactionBuilder {
+    /// Create a new builder talking to `rpc_url`
+    pub fn new(rpc_url: &str) -> Self {
+        Self::from_rpc(Arc::new(AsyncRpcClient::new(rpc_url.to_string())))
+    }
+
+    /// Create a builder sharing an existing RPC backend
+    pub fn from_rpc(rpc: Arc<dyn SolanaRpc>) -> Self {
+        Self {
+            rpc,
+            options: PaymentOptions::default(),
+        }
+    }
+
+    /// Add a memo instruction (e.g. a server-issued nonce) to every payment built
+    pub fn with_memo(mut self, memo: impl Into<String>) -> Self {
+        self.options.memo = Some(memo.into());
+        self
+    }
+
+    /// Let another account (e.g. the facilitator) pay the network fee
+    pub fn with_fee_payer(mut self, fee_payer: Pubkey) -> Self {
+        self.options.fee_payer = Some(fee_payer);
+        self
+    }
+
+    /// Underlying RPC backend
+    pub fn rpc(&self) -> &dyn SolanaRpc {
+        self.rpc.as_ref()
+    }
+
+    /// Network fee `from` pays for a transaction made of `instructions`,
+    /// unless another account pays it
+    async fn fee_for(&self, from: &Pubkey, instructions: &[Instruction]) -> Result<u64, X402Error> {
+        if matches!(self.options.fee_payer, Some(fee_payer) if fee_payer != *from) {
+            return Ok(0);
+        }
+        // The fee is quoted for a message with a current blockhash
+        let recent_blockhash = self.rpc.get_latest_blockhash().await?;
+        let transaction = self.options.unsigned_transaction(instructions, from, recent_blockhash);
+        self.rpc.get_fee_for_message(&transaction.message).await
+    }
+
+    /// Fail with [`X402Error::InsufficientFunds`] unless `from` holds at least
+    /// `required` lamports
+    async fn check_lamports(&self, from: &Pubkey, required: u64) -> Result<(), X402Error> {
+        if required == 0 {
+            return Ok(());
+        }
+        let available = self.rpc.get_balance(from).await?;
+        if available < required {
+            return Err(X402Error::InsufficientFunds {
+                asset: None,
+                required,
+                available,
+            });
+        }
+        Ok(())
+    }
+
+    /// Check that `from` can pay `amount_lamports` to `to` plus the network fee,
+    /// before signing
+    pub async fn check_payment_funds(
+        &self,
+        from: &Pubkey,
+        to: &Pubkey,
+        amount_lamports: u64,
+    ) -> Result<(), X402Error> {
+        let instructions = self.options.sol_payment_instructions(from, to, amount_lamports);
+        let fee = self.fee_for(from, &instructions).await?;
+        self.check_lamports(from, amount_lamports.saturating_add(fee)).await
+    }
+
+    /// Check that `from` holds `amount` of `token_mint`, and the lamports for the
+    /// network fee and the rent of any associated token account the payment has
+    /// to create, before signing
+    pub async fn check_spl_token_payment_funds(
+        &self,
+        from: &Pubkey,
+        to_owner: &Pubkey,
+        token_mint: &Pubkey,
+        amount: u64,
+        decimals: u8,
+    ) -> Result<(), X402Error> {
+        let available = self.rpc.get_token_balance(from, token_mint).await?;
+        if available.unwrap_or(0) < amount {
+            return Err(X402Error::InsufficientFunds {
+                asset: Some(token_mint.to_string()),
+                required: amount,
+                available: available.unwrap_or(0),
+            });
+        }
+
+        // The sender's account exists if it had a balance; the receiver's may not
+        let receiver_ata =
+            spl_associated_token_account::get_associated_token_address(to_owner, token_mint);
+        let sender_ata_exists = available.is_some();
+        let receiver_ata_exists = self.rpc.get_account(&receiver_ata).await?.is_some();
+        let accounts_to_create = u64::from(!sender_ata_exists) + u64::from(!receiver_ata_exists);
+        let rent = if accounts_to_create > 0 {
+            self.rpc
+                .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)
+                .await?
+                * accounts_to_create
+        } else {
+            0
+        };
+
+        let instructions = self.options.spl_payment_instructions(
+            from,
+            to_owner,
+            token_mint,
+            amount,
+            decimals,
+            sender_ata_exists,
+            receiver_ata_exists,
+        )?;
+        let fee = self.fee_for(from, &instructions).await?;
+        self.check_lamports(from, fee.saturating_add(rent)).await
+    }
+
+    /// Create a payment transaction (SOL transfer)
+    pub async fn create_payment_transaction(
+        &self,
+        from: &dyn PaymentSigner,
+        to: &Pubkey,
+        amount_lamports: u64,
+    ) -> Result<SolanaTransaction, X402Error> {
+        let recent_blockhash = self.rpc.get_latest_blockhash().await?;
+        let instructions = self
+            .options
+            .sol_payment_instructions(&from.address(), to, amount_lamports);
+        self.options
+            .signed_transaction_async(&instructions, from, recent_blockhash)
+            .await
+    }
+
+    /// Create an SPL token payment, creating missing associated token accounts
+    pub async fn create_spl_token_payment(
+        &self,
+        from: &dyn PaymentSigner,
+        to_owner: &Pubkey,
+        token_mint: &Pubkey,
+        amount: u64,
+        decimals: u8,
+    ) -> Result<SolanaTransaction, X402Error> {
+        let payer = from.address();
+        let sender_ata = spl_associated_token_account::get_associated_token_address(&payer, token_mint);
+        let receiver_ata =
+            spl_associated_token_account::get_associated_token_address(to_owner, token_mint);
+
+        let instructions = self.options.spl_payment_instructions(
+            &payer,
+            to_owner,
+            token_mint,
+            amount,
+            decimals,
+            matches!(self.rpc.get_account(&sender_ata).await, Ok(Some(_))),
+            matches!(self.rpc.get_account(&receiver_ata).await, Ok(Some(_))),
+        )?;
+
+        let recent_blockhash = self.rpc.get_latest_blockhash().await?;
+        let transaction = self.options.signed_transaction_async(&instructions, from, recent_blockhash).await?;
+
+        Ok(transaction)
+    }
+
+    /// Submit a transaction without waiting for confirmation
+    pub async fn send_transaction(
+        &self,
+        transaction: &SolanaTransaction,
+    ) -> Result<Signature, X402Error> {
+        self.rpc.send_transaction(transaction).await
+    }
+
+    /// Check whether a submitted transaction has been confirmed
+    pub async fn confirm_transaction(&self, signature: &Signature) -> Result<bool, X402Error> {
+        self.rpc.confirm_transaction(signature).await
+    }
+
+    /// Send a transaction and wait until it is confirmed
+    pub async fn send_and_confirm_transaction(
+        &self,
+        transaction: &SolanaTransaction,
+    ) -> Result<Signature, X402Error> {
+        self.rpc.send_and_confirm_transaction(transaction).await
+    }
+
+    /// Lamport balance of an account
+    pub async fn get_balance(&self, pubkey: &Pubkey) -> Result<u64, X402Error> {
+        self.rpc.get_balance(pubkey).await
+    }
+}
+
 /// Helper struct for transaction details
 pub struct Transaction {
     pub id: String,
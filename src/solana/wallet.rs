@@ -1,26 +1,112 @@
+use super::keystore::Keystore;
+use super::rpc::SolanaRpc;
 use crate::error::X402Error;
 use solana_client::rpc_client::RpcClient;
+use solana_derivation_path::DerivationPath;
+use solana_keypair::seed_derivable::keypair_from_seed_and_derivation_path;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::signer::SignerError;
+use std::path::Path;
+use std::sync::Arc;
+use zeroize::Zeroizing;
 
-/// Wallet for managing Solana accounts
+/// BIP44 path of the first Solana account, as used by most wallets
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/501'/0'/0'";
+
+/// Wallet for managing Solana accounts.
+///
+/// Clones share the same keypair, so one wallet can back several fetchers or tasks.
+#[derive(Clone)]
 pub struct Wallet {
-    keypair: Keypair,
+    keypair: Arc<Keypair>,
 }
 
 impl Wallet {
     /// Create a new wallet with a new keypair
     pub fn new() -> Self {
-        let keypair = Keypair::new();
-        Self { keypair }
+        Self::from_keypair(Keypair::new())
+    }
+
+    /// Create a wallet from an existing keypair
+    pub fn from_keypair(keypair: Keypair) -> Self {
+        Self {
+            keypair: Arc::new(keypair),
+        }
     }
 
-    /// Create wallet from a base58 encoded private key
+    /// Create wallet from a base58 encoded 64-byte keypair
     pub fn from_private_key(private_key: &str) -> Result<Self, X402Error> {
-        // Solana 3.0: Use from_base58_string instead of from_bytes
-        let keypair = Keypair::from_base58_string(private_key);
-        
-        Ok(Self { keypair })
+        let bytes = Zeroizing::new(
+            bs58::decode(private_key.trim())
+                .into_vec()
+                .map_err(|e| X402Error::WalletError(format!("Invalid base58 private key: {}", e)))?,
+        );
+        Self::from_bytes(&bytes)
+    }
+
+    /// Create a wallet from the 64 bytes of a keypair (secret key followed by public key)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, X402Error> {
+        let keypair = Keypair::try_from(bytes)
+            .map_err(|e| X402Error::WalletError(format!("Invalid keypair: {}", e)))?;
+        Ok(Self::from_keypair(keypair))
+    }
+
+    /// Create a wallet from `solana-keygen` JSON, a byte array such as `[12,34,...]`
+    pub fn from_keypair_json(json: &str) -> Result<Self, X402Error> {
+        let bytes: Zeroizing<Vec<u8>> = Zeroizing::new(
+            serde_json::from_str(json)
+                .map_err(|e| X402Error::WalletError(format!("Invalid keypair JSON: {}", e)))?,
+        );
+        Self::from_bytes(&bytes)
+    }
+
+    /// Load a wallet from a `solana-keygen` keypair file (e.g. `~/.config/solana/id.json`)
+    pub fn from_keypair_file(path: impl AsRef<Path>) -> Result<Self, X402Error> {
+        Self::from_keypair_json(&read_file(path.as_ref())?)
+    }
+
+    /// Derive a wallet from a BIP39 seed phrase.
+    ///
+    /// The phrase's checksum is validated. `derivation_path` defaults to
+    /// `m/44'/501'/0'/0'`, the account used by Phantom, Solflare and
+    /// `solana-keygen recover 'prompt://?key=0/0'`; every level is hardened.
+    pub fn from_mnemonic(
+        phrase: &str,
+        passphrase: &str,
+        derivation_path: Option<&str>,
+    ) -> Result<Self, X402Error> {
+        let mnemonic = bip39::Mnemonic::parse_normalized(phrase)
+            .map_err(|e| X402Error::WalletError(format!("Invalid seed phrase: {}", e)))?;
+        let path = derivation_path.unwrap_or(DEFAULT_DERIVATION_PATH);
+        let derivation_path = DerivationPath::from_absolute_path_str(path).map_err(|e| {
+            X402Error::WalletError(format!("Invalid derivation path {}: {}", path, e))
+        })?;
+
+        let seed = Zeroizing::new(mnemonic.to_seed(passphrase));
+        let keypair = keypair_from_seed_and_derivation_path(seed.as_ref(), Some(derivation_path))
+            .map_err(|e| X402Error::WalletError(format!("Key derivation failed: {}", e)))?;
+        Ok(Self::from_keypair(keypair))
+    }
+
+    /// Load a wallet from a password-encrypted [`Keystore`] file
+    pub fn from_keystore_file(path: impl AsRef<Path>, password: &str) -> Result<Self, X402Error> {
+        let keystore: Keystore = serde_json::from_str(&read_file(path.as_ref())?)
+            .map_err(|e| X402Error::WalletError(format!("Invalid keystore file: {}", e)))?;
+        Ok(Self::from_keypair(keystore.decrypt(password)?))
+    }
+
+    /// Encrypt this wallet's keypair with `password`
+    pub fn to_keystore(&self, password: &str) -> Result<Keystore, X402Error> {
+        Keystore::encrypt(&self.keypair, password)
+    }
+
+    /// Write this wallet to a password-encrypted [`Keystore`] file
+    pub fn write_keystore_file(&self, path: impl AsRef<Path>, password: &str) -> Result<(), X402Error> {
+        let json = serde_json::to_string_pretty(&self.to_keystore(password)?)?;
+        std::fs::write(path.as_ref(), json).map_err(|e| {
+            X402Error::WalletError(format!("Failed to write {}: {}", path.as_ref().display(), e))
+        })
     }
 
     /// Get the public key of this wallet
@@ -52,6 +138,40 @@ impl Wallet {
             .map_err(|e| X402Error::SolanaError(format!("Failed to get balance: {}", e)))?;
         Ok(balance)
     }
+
+    /// Get the balance of this wallet without blocking the async runtime
+    pub async fn get_balance_async(&self, rpc: &dyn SolanaRpc) -> Result<u64, X402Error> {
+        self.get_balance_for_async(&self.keypair.pubkey(), rpc).await
+    }
+
+    /// Get the balance of a specific pubkey without blocking the async runtime
+    pub async fn get_balance_for_async(
+        &self,
+        pubkey: &Pubkey,
+        rpc: &dyn SolanaRpc,
+    ) -> Result<u64, X402Error> {
+        rpc.get_balance(pubkey).await
+    }
+}
+
+impl Signer for Wallet {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        self.keypair.try_pubkey()
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.keypair.try_sign_message(message)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+impl From<Keypair> for Wallet {
+    fn from(keypair: Keypair) -> Self {
+        Self::from_keypair(keypair)
+    }
 }
 
 impl Default for Wallet {
@@ -63,4 +183,11 @@ impl Default for Wallet {
 /// Create a signer (wallet) from network and private key
 pub fn create_signer(_network: &str, private_key: &str) -> Result<Wallet, X402Error> {
     Wallet::from_private_key(private_key)
-}
\ No newline at end of file
+}
+
+/// Read a file that may hold key material, wiping the copy once it is dropped
+fn read_file(path: &Path) -> Result<Zeroizing<String>, X402Error> {
+    std::fs::read_to_string(path)
+        .map(Zeroizing::new)
+        .map_err(|e| X402Error::WalletError(format!("Failed to read {}: {}", path.display(), e)))
+}
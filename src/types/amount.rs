@@ -0,0 +1,122 @@
+use crate::error::X402Error;
+use std::fmt;
+
+/// Decimals of native SOL (1 SOL = 10^9 lamports)
+pub const SOL_DECIMALS: u8 = 9;
+
+/// An exact token amount: an integer number of atomic units plus the token's decimals.
+///
+/// Prices are written either in atomic units (`"1800"`) or, with a decimal point,
+/// in whole units (`"0.0018"`). Both are parsed without floating point, and a
+/// decimal with more precision than the token supports is rejected instead of
+/// being rounded.
+///
+/// ```rust
+/// use x402_sdk_solana_rust::types::Amount;
+///
+/// let amount = Amount::parse("0.0018", 6).unwrap();
+/// assert_eq!(amount.atomic(), 1800);
+/// assert_eq!(amount.to_decimal_string(), "0.0018");
+/// assert_eq!(Amount::parse("1800", 6).unwrap(), amount);
+/// assert!(Amount::parse("0.0000001", 6).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Amount {
+    atomic: u64,
+    decimals: u8,
+}
+
+impl Amount {
+    /// Amount of `atomic` units of a token with `decimals` decimals
+    pub fn from_atomic(atomic: u64, decimals: u8) -> Self {
+        Self { atomic, decimals }
+    }
+
+    /// Parse a price: atomic units without a decimal point, whole units with one
+    pub fn parse(amount: &str, decimals: u8) -> Result<Self, X402Error> {
+        let amount = amount.trim();
+        if amount.contains('.') {
+            return Self::parse_units(amount, decimals);
+        }
+        if amount.is_empty() || !amount.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(X402Error::InvalidInput(format!("Invalid amount: {:?}", amount)));
+        }
+        let atomic = amount
+            .parse()
+            .map_err(|_| X402Error::InvalidInput(format!("Amount {} is too large", amount)))?;
+        Ok(Self::from_atomic(atomic, decimals))
+    }
+
+    /// Parse a human-readable amount in whole units, e.g. `"1"` or `"0.0018"`
+    pub fn parse_units(amount: &str, decimals: u8) -> Result<Self, X402Error> {
+        let amount = amount.trim();
+        let invalid = || X402Error::InvalidInput(format!("Invalid amount: {:?}", amount));
+
+        let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+        if (whole.is_empty() && fraction.is_empty())
+            || !whole.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        // Trailing zeros carry no precision
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.len() > decimals as usize {
+            return Err(X402Error::InvalidInput(format!(
+                "Amount {} has more than {} decimal places",
+                amount, decimals
+            )));
+        }
+
+        let too_large = || X402Error::InvalidInput(format!("Amount {} is too large", amount));
+        let scale = 10u64.checked_pow(decimals as u32).ok_or_else(too_large)?;
+        let whole: u64 = match whole {
+            "" => 0,
+            whole => whole.parse().map_err(|_| too_large())?,
+        };
+        let fraction: u64 = match fraction {
+            "" => 0,
+            fraction => {
+                let padded = format!("{:0<width$}", fraction, width = decimals as usize);
+                padded.parse().map_err(|_| invalid())?
+            }
+        };
+        let atomic = whole
+            .checked_mul(scale)
+            .and_then(|atomic| atomic.checked_add(fraction))
+            .ok_or_else(too_large)?;
+        Ok(Self::from_atomic(atomic, decimals))
+    }
+
+    /// Number of atomic units (lamports for SOL)
+    pub fn atomic(&self) -> u64 {
+        self.atomic
+    }
+
+    /// Decimals of the token this amount is denominated in
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    /// Atomic units as a string, e.g. `"1800"`
+    pub fn to_atomic_string(&self) -> String {
+        self.atomic.to_string()
+    }
+
+    /// Whole units as a string without trailing zeros, e.g. `"0.0018"`
+    pub fn to_decimal_string(&self) -> String {
+        let decimals = self.decimals as usize;
+        let digits = format!("{:0>width$}", self.atomic, width = decimals + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - decimals);
+        match fraction.trim_end_matches('0') {
+            "" => whole.to_string(),
+            fraction => format!("{}.{}", whole, fraction),
+        }
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_decimal_string())
+    }
+}
@@ -1,10 +1,17 @@
+mod amount;
 mod payment;
 mod request;
 
+pub use amount::{Amount, SOL_DECIMALS};
 pub use payment::*;
 pub use request::*;
 
+use crate::error::X402Error;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
 
 /// Supported networks
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -15,8 +22,19 @@ pub enum Network {
     Solana,
 }
 
+impl Network {
+    /// Public RPC endpoint used when no RPC URL is configured
+    pub fn default_rpc_url(&self) -> &'static str {
+        match self {
+            Network::SolanaLocalnet => "http://127.0.0.1:8899",
+            Network::SolanaDevnet => "https://api.devnet.solana.com",
+            Network::Solana => "https://api.mainnet-beta.solana.com",
+        }
+    }
+}
+
 /// X402 Configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct X402Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub svm_config: Option<SvmConfig>,
@@ -79,9 +97,70 @@ pub enum PaymentScheme {
     Exact,
 }
 
+/// Price charged for a request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Price {
+    /// Amount in atomic units (`"1800"`) or whole units (`"0.0018"`); see [`Amount::parse`]
+    pub amount: String,
+    /// SPL token to pay in; `None` means native SOL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset: Option<TokenConfig>,
+}
+
+impl Price {
+    /// Parse the amount against the asset's decimals (or SOL's)
+    pub fn amount(&self) -> Result<Amount, X402Error> {
+        let decimals = self.asset.as_ref().map_or(SOL_DECIMALS, |token| token.decimals);
+        Amount::parse(&self.amount, decimals)
+    }
+}
+
+/// Request details passed to a dynamic pricing function
+#[derive(Debug, Clone)]
+pub struct PricingRequest {
+    pub method: String,
+    pub path: String,
+    /// Path parameters captured by the matched route pattern
+    pub params: HashMap<String, String>,
+    /// Request headers with lowercase names
+    pub headers: HashMap<String, String>,
+    /// Request body size taken from `content-length`, if known
+    pub body_size: Option<u64>,
+}
+
+/// Future returned by a [`PricingFn`]
+pub type PricingFuture = Pin<Box<dyn Future<Output = Result<Price, X402Error>> + Send>>;
+
+/// Async function computing the price of a request for a route
+#[derive(Clone)]
+pub struct PricingFn(Arc<dyn Fn(PricingRequest) -> PricingFuture + Send + Sync>);
+
+impl PricingFn {
+    /// Wrap an async pricing closure
+    pub fn new<F, Fut>(f: F) -> Self
+    where
+        F: Fn(PricingRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Price, X402Error>> + Send + 'static,
+    {
+        Self(Arc::new(move |request| Box::pin(f(request))))
+    }
+
+    /// Compute the price for a request
+    pub async fn price(&self, request: PricingRequest) -> Result<Price, X402Error> {
+        (self.0)(request).await
+    }
+}
+
+impl std::fmt::Debug for PricingFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PricingFn")
+    }
+}
+
 /// Route Configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RouteConfig {
+    /// Price in atomic units or whole units of the default token (or SOL); see [`Amount::parse`]
     pub price: String,
     pub network: Network,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -92,12 +171,10 @@ pub struct RouteConfig {
     pub max_timeout_seconds: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub discoverable: Option<bool>,
-}
-
-impl Default for X402Config {
-    fn default() -> Self {
-        Self {
-            svm_config: None,
-        }
-    }
+    /// Optional dynamic pricing; when set it replaces `price` and the default token
+    #[serde(skip)]
+    pub pricing: Option<PricingFn>,
+    /// Additional payment options offered alongside `price`, e.g. SOL next to USDC
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accepts: Vec<Price>,
 }
\ No newline at end of file
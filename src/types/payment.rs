@@ -1,27 +1,153 @@
-use serde::{Deserialize, Serialize};
-use super::{Network, PaymentScheme};
+use crate::error::X402Error;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+use super::{Amount, Network, PaymentScheme, SOL_DECIMALS};
 
-/// Payment Requirements returned by the server in 402 response
+/// Payment Requirements returned by the server in 402 response.
+///
+/// Field set follows the x402 v1 `PaymentRequirements` schema. `asset` is the
+/// SPL token mint (`None` for native SOL, sent as [`NATIVE_SOL_ASSET`]) and token
+/// metadata such as `decimals` and `name` lives in `extra`. Payloads using the legacy
+/// `tokenAddress`, `tokenDecimals`, `tokenName` and `memo` fields still
+/// deserialize.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", from = "PaymentRequirementsRepr")]
 pub struct PaymentRequirements {
-    pub x402_version: u8,
     pub scheme: PaymentScheme,
     pub network: Network,
     pub max_amount_required: String,
+    pub resource: String,
+    pub description: String,
+    pub mime_type: String,
     pub pay_to: String,
+    pub max_timeout_seconds: u64,
+    #[serde(serialize_with = "serialize_asset")]
+    pub asset: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub token_address: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub token_decimals: Option<u8>,
+    pub output_schema: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub token_name: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub memo: Option<String>,
+    pub extra: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub nonce: Option<String>,
 }
 
+/// Default `maxTimeoutSeconds` when a route does not configure one
+pub const DEFAULT_MAX_TIMEOUT_SECONDS: u64 = 60;
+
+/// `asset` naming native SOL, as the TypeScript SDK sends it: the wrapped SOL
+/// mint (`NATIVE_MINT` in `@solana/spl-token`)
+pub const NATIVE_SOL_ASSET: &str = "So11111111111111111111111111111111111111112";
+
+/// Always emit `asset`, since clients of the spec expect it on every option
+fn serialize_asset<S: Serializer>(asset: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(asset.as_deref().unwrap_or(NATIVE_SOL_ASSET))
+}
+
+impl PaymentRequirements {
+    /// Token decimals from `extra.decimals`, if the requirements name an SPL token
+    pub fn token_decimals(&self) -> Option<u8> {
+        self.extra
+            .as_ref()?
+            .get("decimals")?
+            .as_u64()
+            .and_then(|d| u8::try_from(d).ok())
+    }
+
+    /// Token name from `extra.name`, if present
+    pub fn token_name(&self) -> Option<&str> {
+        self.extra.as_ref()?.get("name")?.as_str()
+    }
+
+    /// Facilitator that pays the network fee, from `extra.feePayer`
+    pub fn fee_payer(&self) -> Option<&str> {
+        self.extra.as_ref()?.get("feePayer")?.as_str()
+    }
+
+    /// `max_amount_required` parsed against the token's decimals (or SOL's)
+    pub fn amount(&self) -> Result<Amount, X402Error> {
+        let decimals = self.token_decimals().unwrap_or(SOL_DECIMALS);
+        Amount::parse(&self.max_amount_required, decimals)
+    }
+}
+
+/// Wire representation accepting both the spec and the legacy field names
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PaymentRequirementsRepr {
+    scheme: PaymentScheme,
+    network: Network,
+    max_amount_required: String,
+    #[serde(default)]
+    resource: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    mime_type: String,
+    pay_to: String,
+    #[serde(default)]
+    max_timeout_seconds: Option<u64>,
+    #[serde(default, alias = "tokenAddress")]
+    asset: Option<String>,
+    #[serde(default)]
+    output_schema: Option<Value>,
+    #[serde(default)]
+    extra: Option<Value>,
+    #[serde(default)]
+    nonce: Option<String>,
+    // Legacy fields
+    #[serde(default)]
+    token_decimals: Option<u8>,
+    #[serde(default)]
+    token_name: Option<String>,
+    #[serde(default)]
+    memo: Option<String>,
+}
+
+impl From<PaymentRequirementsRepr> for PaymentRequirements {
+    fn from(repr: PaymentRequirementsRepr) -> Self {
+        let mut extra = repr.extra;
+        if repr.token_decimals.is_some() || repr.token_name.is_some() {
+            let map = extra.get_or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(map) = map {
+                if let Some(decimals) = repr.token_decimals {
+                    map.entry("decimals").or_insert(decimals.into());
+                }
+                if let Some(name) = repr.token_name {
+                    map.entry("name").or_insert(name.into());
+                }
+            }
+        }
+
+        Self {
+            scheme: repr.scheme,
+            network: repr.network,
+            max_amount_required: repr.max_amount_required,
+            resource: repr.resource,
+            description: repr.description.or(repr.memo).unwrap_or_default(),
+            mime_type: repr.mime_type,
+            pay_to: repr.pay_to,
+            max_timeout_seconds: repr
+                .max_timeout_seconds
+                .unwrap_or(DEFAULT_MAX_TIMEOUT_SECONDS),
+            asset: repr.asset.filter(|asset| asset != NATIVE_SOL_ASSET),
+            output_schema: repr.output_schema,
+            extra,
+            nonce: repr.nonce,
+        }
+    }
+}
+
+/// Body of a 402 response listing every accepted way to pay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequiredResponse {
+    pub x402_version: u8,
+    pub error: String,
+    pub accepts: Vec<PaymentRequirements>,
+}
+
 /// Payment Payload sent by client with payment proof
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -33,24 +159,92 @@ pub struct PaymentPayload {
     pub from: String, // Sender's public key
 }
 
-/// Verify Response from facilitator
+impl PaymentPayload {
+    /// Encode the payload as an `X-PAYMENT` header value (base64 JSON)
+    pub fn to_header(&self) -> Result<String, X402Error> {
+        encode_header(self)
+    }
+
+    /// Decode an `X-PAYMENT` header value, accepting base64 JSON or legacy raw JSON
+    pub fn from_header(value: &str) -> Result<Self, X402Error> {
+        decode_header(value)
+    }
+}
+
+/// Verify Response from facilitator.
+///
+/// Serialized with the x402 spec names (`isValid`, `invalidReason`, `payer`);
+/// the legacy `verified` is still accepted.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct VerifyResponse {
+    #[serde(rename = "isValid", alias = "verified")]
     pub verified: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message: Option<String>,
+    /// Machine-readable rejection code, e.g. `amount_mismatch`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invalid_reason: Option<String>,
+    /// Address that authorized the payment, when verified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payer: Option<String>,
 }
 
-/// Settle Response from facilitator
+/// Settle Response from facilitator.
+///
+/// Serialized with the x402 spec names (`success`, `transaction`, `network`,
+/// `payer`, `errorReason`); the legacy `settled` and `signature` are still accepted.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SettleResponse {
+    #[serde(rename = "transaction", alias = "signature")]
     pub signature: String,
+    #[serde(rename = "success", alias = "settled")]
     pub settled: bool,
+    /// Network the transaction was submitted to; missing from legacy receipts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<Network>,
+    /// Address that paid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payer: Option<String>,
+    /// Machine-readable reason the payment was not settled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_reason: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message: Option<String>,
 }
 
+impl SettleResponse {
+    /// Encode the receipt as an `X-PAYMENT-RESPONSE` header value (base64 JSON)
+    pub fn to_header(&self) -> Result<String, X402Error> {
+        encode_header(self)
+    }
+
+    /// Decode an `X-PAYMENT-RESPONSE` header value, accepting base64 or raw JSON
+    pub fn from_header(value: &str) -> Result<Self, X402Error> {
+        decode_header(value)
+    }
+}
+
+/// Serialize a value to JSON and base64-encode it for use in a header
+fn encode_header<T: Serialize>(value: &T) -> Result<String, X402Error> {
+    Ok(STANDARD.encode(serde_json::to_vec(value)?))
+}
+
+/// Decode a base64 JSON header value, falling back to raw JSON
+fn decode_header<T: DeserializeOwned>(value: &str) -> Result<T, X402Error> {
+    let value = value.trim();
+    if value.starts_with('{') {
+        return serde_json::from_str(value)
+            .map_err(|e| X402Error::DeserializationError(e.to_string()));
+    }
+
+    let decoded = STANDARD
+        .decode(value)
+        .map_err(|e| X402Error::DeserializationError(format!("Invalid base64 header: {}", e)))?;
+    serde_json::from_slice(&decoded).map_err(|e| X402Error::DeserializationError(e.to_string()))
+}
+
 /// Supported Payment Kind
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -58,6 +252,16 @@ pub struct SupportedPaymentKind {
     pub x402_version: u8,
     pub scheme: PaymentScheme,
     pub network: Network,
+    /// Scheme-specific details, e.g. `{"feePayer": "<pubkey>"}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<Value>,
+}
+
+impl SupportedPaymentKind {
+    /// Facilitator that pays the network fee, from `extra.feePayer`
+    pub fn fee_payer(&self) -> Option<&str> {
+        self.extra.as_ref()?.get("feePayer")?.as_str()
+    }
 }
 
 /// Supported Payment Kinds Response
@@ -1,4 +1,8 @@
+use super::SettleResponse;
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Request {
@@ -10,9 +14,110 @@ pub struct Request {
 #[derive(Debug, Clone)]
 pub struct X402Request {
     pub url: String,
+    /// Any HTTP method, e.g. `GET`, `PATCH` or `OPTIONS`
     pub method: String,
     pub headers: std::collections::HashMap<String, String>,
-    pub body: Option<Vec<u8>>,
+    pub body: Option<RequestBody>,
+}
+
+impl X402Request {
+    /// A request without headers or body
+    pub fn new(method: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            method: method.into(),
+            headers: std::collections::HashMap::new(),
+            body: None,
+        }
+    }
+
+    /// Add a header
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the body
+    pub fn with_body(mut self, body: impl Into<RequestBody>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+}
+
+/// Request body that can be sent more than once.
+///
+/// A paid request is sent at least twice: once to learn the price and again
+/// with the payment, plus any retries. In-memory bytes are shared between the
+/// attempts without copying; a stream is opened anew for every attempt.
+///
+/// ```rust,ignore
+/// let body = RequestBody::stream(|| {
+///     let rows = vec![Ok::<_, std::io::Error>("id,amount\n"), Ok("1,100\n")];
+///     reqwest::Body::wrap_stream(futures_util::stream::iter(rows))
+/// });
+/// let request = X402Request::new("PUT", "https://api.example.com/upload").with_body(body);
+/// ```
+#[derive(Clone)]
+pub enum RequestBody {
+    Bytes(Bytes),
+    Stream(Arc<dyn Fn() -> reqwest::Body + Send + Sync>),
+}
+
+impl RequestBody {
+    /// A streaming body re-created by `open` for every attempt
+    pub fn stream<F>(open: F) -> Self
+    where
+        F: Fn() -> reqwest::Body + Send + Sync + 'static,
+    {
+        Self::Stream(Arc::new(open))
+    }
+
+    /// Body for one attempt
+    pub fn to_body(&self) -> reqwest::Body {
+        match self {
+            RequestBody::Bytes(bytes) => reqwest::Body::from(bytes.clone()),
+            RequestBody::Stream(open) => open(),
+        }
+    }
+}
+
+impl fmt::Debug for RequestBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestBody::Bytes(bytes) => write!(f, "RequestBody::Bytes({} bytes)", bytes.len()),
+            RequestBody::Stream(_) => f.write_str("RequestBody::Stream"),
+        }
+    }
+}
+
+impl From<Bytes> for RequestBody {
+    fn from(bytes: Bytes) -> Self {
+        RequestBody::Bytes(bytes)
+    }
+}
+
+impl From<Vec<u8>> for RequestBody {
+    fn from(bytes: Vec<u8>) -> Self {
+        RequestBody::Bytes(bytes.into())
+    }
+}
+
+impl From<String> for RequestBody {
+    fn from(text: String) -> Self {
+        RequestBody::Bytes(text.into())
+    }
+}
+
+impl From<&'static str> for RequestBody {
+    fn from(text: &'static str) -> Self {
+        RequestBody::Bytes(Bytes::from_static(text.as_bytes()))
+    }
+}
+
+impl From<&'static [u8]> for RequestBody {
+    fn from(bytes: &'static [u8]) -> Self {
+        RequestBody::Bytes(Bytes::from_static(bytes))
+    }
 }
 
 /// HTTP Response with Payment Information
@@ -21,4 +126,6 @@ pub struct X402Response {
     pub status: u16,
     pub headers: std::collections::HashMap<String, String>,
     pub body: Vec<u8>,
+    /// Settlement receipt parsed from the `X-PAYMENT-RESPONSE` header, if any
+    pub payment_response: Option<SettleResponse>,
 }
\ No newline at end of file
@@ -0,0 +1,233 @@
+// Helpers shared by the integration tests: stand-in services and a paid test server.
+
+use http::{Request, Response};
+use serde_json::{json, Value};
+use solana_sdk::{
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    transaction::Transaction,
+};
+use solana_system_interface::instruction as system_instruction;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use x402_sdk_solana_rust::facilitator::{server, FacilitatorService};
+use x402_sdk_solana_rust::server::{PaymentLayer, PaymentMiddlewareConfig};
+use x402_sdk_solana_rust::solana::{InMemoryLedger, TransactionBuilder, MEMO_PROGRAM_ID};
+use x402_sdk_solana_rust::types::{
+    FacilitatorConfig, Network, PaymentPayload, PaymentScheme, Price, RouteConfig, SvmConfig,
+    TokenConfig, X402Config, X402Request,
+};
+
+/// Serve `app` on a free local port and return its base URL
+pub async fn serve(app: axum::Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    url
+}
+
+/// Start a stand-in JSON-RPC node.
+///
+/// `answer` is called with the method and params of each request and returns the
+/// `value` of the result, which is wrapped in the usual `{ context, value }` envelope.
+pub async fn stub_rpc<F>(answer: F) -> String
+where
+    F: Fn(&str, &Value) -> Value + Clone + Send + Sync + 'static,
+{
+    let handler = move |axum::Json(request): axum::Json<Value>| {
+        let value = answer(request["method"].as_str().unwrap_or_default(), &request["params"]);
+        async move {
+            axum::Json(json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": { "context": { "slot": 1 }, "value": value }
+            }))
+        }
+    };
+    serve(axum::Router::new().route("/", axum::routing::post(handler))).await
+}
+
+/// Start a stand-in facilitator that answers `/verify` with `verify` and `/settle`
+/// with the `settle` status and body
+pub async fn stub_facilitator(verify: Value, settle: (u16, Value)) -> FacilitatorConfig {
+    let (status, receipt) = settle;
+    let settle = move || async move {
+        let status = http::StatusCode::from_u16(status).unwrap();
+        (status, axum::Json(receipt))
+    };
+    let app = axum::Router::new()
+        .route("/verify", axum::routing::post(move || async move { axum::Json(verify) }))
+        .route("/settle", axum::routing::post(settle));
+    FacilitatorConfig {
+        url: serve(app).await,
+        create_auth_headers: None,
+    }
+}
+
+/// A signed devnet payment of 1000 lamports to `pay_to`, committing to `memo` if given
+pub fn sol_payment(pay_to: &Pubkey, memo: Option<&str>) -> PaymentPayload {
+    let payer = Keypair::new();
+    let mut instructions = Vec::new();
+    if let Some(memo) = memo {
+        instructions.push(Instruction::new_with_bytes(
+            MEMO_PROGRAM_ID,
+            memo.as_bytes(),
+            vec![AccountMeta::new_readonly(payer.pubkey(), true)],
+        ));
+    }
+    instructions.push(system_instruction::transfer(&payer.pubkey(), pay_to, 1000));
+    let message = Message::new(&instructions, Some(&payer.pubkey()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.sign(&[&payer], Hash::default());
+
+    PaymentPayload {
+        x402_version: 1,
+        scheme: PaymentScheme::Exact,
+        network: Network::SolanaDevnet,
+        signed_transaction: TransactionBuilder::serialize_transaction(&transaction).unwrap(),
+        from: payer.pubkey().to_string(),
+    }
+}
+
+/// A plain `GET` of `url`
+pub fn get(url: String) -> X402Request {
+    X402Request {
+        url,
+        method: "GET".to_string(),
+        headers: HashMap::new(),
+        body: None,
+    }
+}
+
+/// A 6-decimal token named USDC for `mint`
+pub fn usdc(mint: &Pubkey) -> TokenConfig {
+    TokenConfig {
+        address: mint.to_string(),
+        decimals: 6,
+        name: "USDC".to_string(),
+    }
+}
+
+type Configure = Box<dyn FnOnce(PaymentMiddlewareConfig) -> PaymentMiddlewareConfig>;
+
+/// A facilitator and a resource server charging for `/paid`, settling on an
+/// [`InMemoryLedger`].
+///
+/// `GET /paid` answers "paid content"; other methods echo the method and body.
+pub struct TestServer {
+    ledger: Arc<InMemoryLedger>,
+    pay_to: Pubkey,
+    facilitator: FacilitatorService,
+    price: String,
+    token: Option<TokenConfig>,
+    accepts: Vec<Price>,
+    configure: Configure,
+}
+
+impl TestServer {
+    /// Charge 1000 lamports paid to `pay_to`, through a devnet facilitator without a fee payer
+    pub fn new(ledger: &Arc<InMemoryLedger>, pay_to: &Pubkey) -> Self {
+        Self {
+            ledger: ledger.clone(),
+            pay_to: *pay_to,
+            facilitator: FacilitatorService::new(Network::SolanaDevnet),
+            price: "1000".to_string(),
+            token: None,
+            accepts: Vec::new(),
+            configure: Box::new(|config| config),
+        }
+    }
+
+    pub fn facilitator(mut self, facilitator: FacilitatorService) -> Self {
+        self.facilitator = facilitator;
+        self
+    }
+
+    pub fn price(mut self, price: &str) -> Self {
+        self.price = price.to_string();
+        self
+    }
+
+    /// Charge in `token` instead of SOL
+    pub fn token(mut self, token: TokenConfig) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Also offer the alternative prices in `accepts`
+    pub fn accepts(mut self, accepts: Vec<Price>) -> Self {
+        self.accepts = accepts;
+        self
+    }
+
+    /// Adjust the server config before it is used
+    pub fn configure(
+        mut self,
+        configure: impl FnOnce(PaymentMiddlewareConfig) -> PaymentMiddlewareConfig + 'static,
+    ) -> Self {
+        self.configure = Box::new(configure);
+        self
+    }
+
+    /// Start both servers and return the URL of `/paid`
+    pub async fn start(self) -> String {
+        let fee_payer = self.facilitator.fee_payer();
+        let facilitator = self.facilitator.with_rpc(self.ledger);
+        let facilitator_url = serve(server::router(Arc::new(facilitator))).await;
+
+        let mut routes = HashMap::new();
+        routes.insert(
+            "ANY /paid".to_string(),
+            RouteConfig {
+                price: self.price,
+                network: Network::SolanaDevnet,
+                description: Some("Paid content".to_string()),
+                mime_type: None,
+                max_timeout_seconds: None,
+                discoverable: None,
+                pricing: None,
+                accepts: self.accepts,
+            },
+        );
+        let x402_config = self.token.map(|token| X402Config {
+            svm_config: Some(SvmConfig {
+                rpc_url: None,
+                default_token: Some(token),
+            }),
+        });
+        let mut config = PaymentMiddlewareConfig::new(
+            self.pay_to.to_string(),
+            routes,
+            Some(FacilitatorConfig {
+                url: facilitator_url,
+                create_auth_headers: None,
+            }),
+            x402_config,
+        )
+        .unwrap();
+        if let Some(fee_payer) = fee_payer {
+            config = config.with_fee_payer(fee_payer.to_string());
+        }
+        let config = (self.configure)(config);
+
+        let handler = tower::service_fn(|req: Request<axum::body::Body>| async move {
+            let body = match req.method().to_string() {
+                method if method == "GET" => "paid content".to_string(),
+                method => {
+                    let body = axum::body::to_bytes(req.into_body(), usize::MAX).await.unwrap();
+                    format!("{} {}", method, String::from_utf8_lossy(&body))
+                }
+            };
+            Ok::<_, Infallible>(Response::new(axum::body::Body::from(body)))
+        });
+        let app = axum::Router::new()
+            .route_service("/paid", handler)
+            .layer(PaymentLayer::new(config));
+        format!("{}/paid", serve(app).await)
+    }
+}